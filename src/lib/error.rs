@@ -368,13 +368,34 @@ pub enum PostgresConnectError {
     PgConnectDbError(PostgresDbError),
     /// A password was required but not provided in the URL
     MissingPassword,
+    /// The server requested the password in cleartext over a connection
+    /// which is not encrypted with SSL, and `allow_insecure_cleartext` was
+    /// not set
+    InsecureCleartextPassword,
     /// The Postgres server requested an authentication method not supported
-    /// by the driver
-    UnsupportedAuthentication,
+    /// by the driver. The name of the method is included.
+    ///
+    /// The supported methods are cleartext passwords, MD5 passwords and
+    /// SCRAM-SHA-256. GSSAPI, SSPI, Kerberos and SCM credential
+    /// authentication are not supported.
+    UnsupportedAuthentication(String),
+    /// The server used an authentication method not listed in
+    /// `require_auth`. The name of the method is included.
+    DisallowedAuthentication(String),
     /// The Postgres server does not support SSL encryption
     NoSslSupport,
+    /// The connection parameters require SSL, but `NoSsl` was used
+    MissingSslContext,
+    /// The connection parameters require the server's certificate to be
+    /// verified, but the `SslMode` does not verify it as strictly
+    MissingSslVerification,
     /// There was an error initializing the SSL session
     SslError(SslError),
+    /// The server's certificate could not be verified against the trusted
+    /// certificate authorities
+    SslVerifyError(SslError),
+    /// The server's certificate was not issued for the host being connected to
+    SslHostnameMismatch(String),
     /// There was an error communicating with the server
     PgConnectStreamError(IoError),
     /// The server sent an unexpected response
@@ -392,13 +413,31 @@ impl fmt::Show for PostgresConnectError {
             MissingPassword =>
                 write!(fmt, "The server requested a password but none was \
                              provided"),
-            UnsupportedAuthentication =>
-                write!(fmt, "The server requested an unsupported \
-                             authentication method"),
+            InsecureCleartextPassword =>
+                write!(fmt, "The server requested a cleartext password over a \
+                             connection without SSL"),
+            UnsupportedAuthentication(ref method) =>
+                write!(fmt, "The server requested {} authentication, which is \
+                             not supported. Supported methods are cleartext \
+                             password, MD5 password and SCRAM-SHA-256", method),
+            DisallowedAuthentication(ref method) =>
+                write!(fmt, "The server used {} authentication, which is not \
+                             allowed by require_auth", method),
             NoSslSupport =>
                 write!(fmt, "The server does not support SSL"),
+            MissingSslContext =>
+                write!(fmt, "The connection parameters require SSL, but no SSL \
+                             context was provided"),
+            MissingSslVerification =>
+                write!(fmt, "The connection parameters require the server's \
+                             certificate to be verified, but the SSL mode does \
+                             not verify it"),
             SslError(ref err) =>
                 write!(fmt, "Error initiating SSL session: {}", err),
+            SslVerifyError(ref err) =>
+                write!(fmt, "Unable to verify the server's certificate: {}", err),
+            SslHostnameMismatch(ref host) =>
+                write!(fmt, "The server's certificate is not valid for {}", host),
             PgConnectStreamError(ref err) =>
                 write!(fmt, "Error communicating with server: {}", err),
             PgConnectBadResponse =>
@@ -535,8 +574,24 @@ pub enum PostgresError {
     /// An attempt was made to convert between incompatible Rust and Postgres
     /// types
     PgWrongType(PostgresType),
+    /// A parameter could not be converted to the type the statement expects
+    /// for it. Parameters are checked before anything is sent to the server.
+    PgWrongParamType {
+        /// The position of the parameter, starting from 1 as in `$1`
+        pub index: uint,
+        /// The type the statement expects
+        pub ty: PostgresType,
+    },
     /// An attempt was made to read from a column that does not exist
     PgInvalidColumn,
+    /// A row was converted into a type expecting a different number of
+    /// columns
+    PgWrongColumnCount {
+        /// The expected number of columns
+        pub expected: uint,
+        /// The actual number of columns
+        pub actual: uint,
+    },
     /// A value was NULL but converted to a non-nullable Rust type
     PgWasNull,
     /// An attempt was made to prepare a statement or start a transaction on an
@@ -544,8 +599,30 @@ pub enum PostgresError {
     PgWrongTransaction,
     /// The server returned an unexpected response
     PgBadResponse,
+    /// A statement was executed in a transaction which has been aborted by an
+    /// earlier error. The transaction must be rolled back before it can be
+    /// used again.
+    PgTransactionAborted,
     /// The server provided data that the client could not parse
     PgBadData,
+    /// A value was decoded without consuming exactly the data the server sent
+    /// for it, which indicates a bug in the `FromSql` implementation for its
+    /// type
+    PgBadFieldLength {
+        /// The name of the column holding the value, if known
+        pub column: Option<String>,
+        /// The type of the value
+        pub ty: PostgresType,
+    },
+    /// A query did not complete within its timeout and was canceled
+    PgQueryTimeout,
+    /// An attempt was made to execute a statement which has been deallocated
+    PgStatementDeallocated,
+    /// An interval with a nonzero month component was converted to a
+    /// `Duration`, which cannot represent months since their length varies
+    PgIntervalHasMonths,
+    /// An empty list of values was passed to `expand_in_list`
+    PgEmptyInList,
 }
 
 impl fmt::Show for PostgresError {
@@ -563,17 +640,43 @@ impl fmt::Show for PostgresError {
                 write!(fmt, "Expected {} parameters but got {}", expected,
                        actual),
             PgWrongType(ref ty) => write!(fmt, "Unexpected type {}", ty),
+            PgWrongParamType { index, ref ty } =>
+                write!(fmt, "Parameter ${} has type {}, but the supplied value \
+                             cannot be converted to it", index, ty),
             PgInvalidColumn => write!(fmt, "Invalid column"),
+            PgWrongColumnCount { expected, actual } =>
+                write!(fmt, "Expected {} columns but got {}", expected, actual),
             PgWasNull => write!(fmt, "The value was NULL"),
             PgWrongTransaction =>
                 write!(fmt, "An attempt was made to prepare a statement or \
                              start a transaction on an object other than the \
-                             active transaction"),
+                             active transaction; nested transactions must be \
+                             started from the active transaction"),
             PgBadResponse =>
                 write!(fmt, "The server returned an unexpected response"),
+            PgTransactionAborted =>
+                write!(fmt, "The current transaction has been aborted by an \
+                             earlier error and must be rolled back"),
             PgBadData =>
                 write!(fmt, "The server provided data that the client could \
                              not parse"),
+            PgBadFieldLength { column: Some(ref column), ref ty } =>
+                write!(fmt, "The decoder for type {} over- or under-read the \
+                             value of column {}", ty, column),
+            PgBadFieldLength { column: None, ref ty } =>
+                write!(fmt, "The decoder for type {} over- or under-read a \
+                             value", ty),
+            PgQueryTimeout =>
+                write!(fmt, "The query did not complete within its timeout \
+                             and was canceled"),
+            PgStatementDeallocated =>
+                write!(fmt, "The statement has been deallocated and can no \
+                             longer be executed"),
+            PgIntervalHasMonths =>
+                write!(fmt, "The interval has a month component and cannot be \
+                             represented as a fixed duration"),
+            PgEmptyInList =>
+                write!(fmt, "An IN list must contain at least one value"),
         }
     }
 }
@@ -1,20 +1,31 @@
+use openssl::nid;
 use openssl::ssl::SslStream;
-use std::io::net::ip::Port;
+use openssl::ssl::error::StreamError;
+use std::ascii::StrAsciiExt;
+use std::io::net::addrinfo;
+use std::io::net::ip::{Port, SocketAddr};
 use std::io::net::tcp::TcpStream;
 use std::io::net::unix::UnixStream;
-use std::io::{Stream, IoResult};
+use std::io::{Stream, IoResult, InvalidInput, standard_error};
+use std::time::Duration;
 
 use {PostgresConnectParams,
      SslMode,
      NoSsl,
      PreferSsl,
      RequireSsl,
+     VerifyCa,
+     VerifyFull,
      TargetTcp,
      TargetUnix};
 use error::{PostgresConnectError,
             PgConnectStreamError,
             NoSslSupport,
+            MissingSslContext,
+            MissingSslVerification,
             SslError,
+            SslVerifyError,
+            SslHostnameMismatch,
             SocketError};
 use message;
 use message::{SslRequest, WriteMessage};
@@ -51,6 +62,22 @@ impl<S: Stream> Writer for MaybeSslStream<S> {
     }
 }
 
+impl MaybeSslStream<InternalStream> {
+    pub fn set_read_timeout(&mut self, timeout_ms: Option<u64>) {
+        match *self {
+            SslStream(ref mut s) => s.get_mut().set_read_timeout(timeout_ms),
+            NormalStream(ref mut s) => s.set_read_timeout(timeout_ms),
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        match *self {
+            SslStream(_) => true,
+            NormalStream(_) => false,
+        }
+    }
+}
+
 pub enum InternalStream {
     TcpStream(TcpStream),
     UnixStream(UnixStream),
@@ -81,30 +108,79 @@ impl Writer for InternalStream {
     }
 }
 
+impl InternalStream {
+    pub fn set_read_timeout(&mut self, timeout_ms: Option<u64>) {
+        match *self {
+            TcpStream(ref mut s) => s.set_read_timeout(timeout_ms),
+            UnixStream(ref mut s) => s.set_read_timeout(timeout_ms),
+        }
+    }
+}
+
 fn open_socket(params: &PostgresConnectParams)
                -> Result<InternalStream, PostgresConnectError> {
     let port = params.port.unwrap_or(DEFAULT_PORT);
     let socket = match params.target {
-        TargetTcp(ref host) =>
-            TcpStream::connect(host.as_slice(), port).map(|s| TcpStream(s)),
+        TargetTcp(ref host) => {
+            open_tcp(host.as_slice(), port, params.connect_timeout).and_then(|mut s| {
+                match params.keepalive {
+                    Some(idle) => try!(s.set_keepalive(Some(idle))),
+                    None => {}
+                }
+                Ok(TcpStream(s))
+            })
+        }
         TargetUnix(ref path) => {
             let mut path = path.clone();
             path.push(format!(".s.PGSQL.{}", port));
-            UnixStream::connect(&path).map(|s| UnixStream(s))
+            let socket = match params.connect_timeout {
+                Some(timeout) => UnixStream::connect_timeout(&path, timeout),
+                None => UnixStream::connect(&path),
+            };
+            socket.map(|s| UnixStream(s))
         }
     };
     socket.map_err(|e| SocketError(e))
 }
 
+fn open_tcp(host: &str, port: Port, timeout: Option<Duration>) -> IoResult<TcpStream> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return TcpStream::connect(host, port),
+    };
+
+    // A timeout can only be applied when connecting to an address, so try
+    // each address of the host in turn
+    let mut last_err = standard_error(InvalidInput);
+    for ip in try!(addrinfo::get_host_addresses(host)).move_iter() {
+        match TcpStream::connect_timeout(SocketAddr { ip: ip, port: port }, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
 pub fn initialize_stream(params: &PostgresConnectParams, ssl: &SslMode)
                          -> Result<MaybeSslStream<InternalStream>,
                                    PostgresConnectError> {
+    let verified = match *ssl {
+        VerifyCa(..) => !params.require_ssl_hostname,
+        VerifyFull(..) => true,
+        _ => !params.require_ssl_verify && !params.require_ssl_hostname,
+    };
+    if !verified {
+        return Err(MissingSslVerification);
+    }
+
     let mut socket = try!(open_socket(params));
 
-    let (ssl_required, ctx) = match *ssl {
+    let (ssl_required, verify, ctx) = match *ssl {
+        NoSsl if params.require_ssl => return Err(MissingSslContext),
         NoSsl => return Ok(NormalStream(socket)),
-        PreferSsl(ref ctx) => (false, ctx),
-        RequireSsl(ref ctx) => (true, ctx)
+        PreferSsl(ref ctx) => (params.require_ssl, false, ctx),
+        RequireSsl(ref ctx) => (true, false, ctx),
+        VerifyCa(ref ctx) | VerifyFull(ref ctx) => (true, true, &ctx.ctx),
     };
 
     try_pg_conn!(socket.write_message(&SslRequest { code: message::SSL_CODE }));
@@ -118,8 +194,53 @@ pub fn initialize_stream(params: &PostgresConnectParams, ssl: &SslMode)
         }
     }
 
-    match SslStream::try_new(ctx, socket) {
-        Ok(stream) => Ok(SslStream(stream)),
-        Err(err) => Err(SslError(err))
+    let stream = match SslStream::try_new(ctx, socket) {
+        Ok(stream) => stream,
+        // I/O errors are failures to communicate, not to verify
+        Err(err @ StreamError(..)) => return Err(SslError(err)),
+        Err(err) if verify => return Err(SslVerifyError(err)),
+        Err(err) => return Err(SslError(err)),
+    };
+
+    match (ssl, &params.target) {
+        (&VerifyFull(..), &TargetTcp(ref host)) => {
+            match peer_name(&stream) {
+                Some(ref name) if hostname_matches(name.as_slice(), host.as_slice()) => {}
+                _ => return Err(SslHostnameMismatch(host.clone())),
+            }
+        }
+        _ => {}
+    }
+
+    Ok(SslStream(stream))
+}
+
+// Returns the common name of the peer's certificate. rust-openssl does not
+// expose a certificate's subject alternative names, so they aren't checked.
+fn peer_name(stream: &SslStream<InternalStream>) -> Option<String> {
+    stream.get_peer_certificate().and_then(|cert| {
+        cert.subject_name().text_by_nid(nid::CN).map(|name| name.to_str())
+    })
+}
+
+// Matches a name from a certificate against a host, following RFC 6125. A
+// wildcard is only allowed as the entire leftmost label, where it matches
+// exactly one label of the host, and must be followed by at least two labels.
+fn hostname_matches(name: &str, host: &str) -> bool {
+    let name = name.to_ascii_lower();
+    let host = host.to_ascii_lower();
+
+    if !name.as_slice().starts_with("*.") {
+        return name == host;
+    }
+
+    let suffix = name.as_slice().slice_from(1);
+    if !suffix.slice_from(1).contains_char('.') {
+        return false;
+    }
+
+    match host.as_slice().find('.') {
+        Some(idx) if idx > 0 => host.as_slice().slice_from(idx) == suffix,
+        _ => false,
     }
 }
@@ -0,0 +1,197 @@
+//! Support for Postgres large objects
+//!
+//! Large objects store binary data which can be read and written in pieces,
+//! rather than all at once like a `BYTEA` value. Postgres only allows large
+//! objects to be accessed inside of a transaction, so every function in this
+//! module takes a `PostgresTransaction`.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use postgres::{PostgresConnection, NoSsl};
+//! use postgres::large_object;
+//! use postgres::large_object::ReadWrite;
+//!
+//! # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+//! let trans = conn.transaction().unwrap();
+//! let oid = large_object::create(&trans).unwrap();
+//! {
+//!     let mut lo = large_object::open(&trans, oid, ReadWrite).unwrap();
+//!     lo.write(b"hello world").unwrap();
+//!     lo.finish().unwrap();
+//! }
+//! trans.finish().unwrap();
+//! ```
+
+use std::io::{IoError, IoResult, EndOfFile, OtherIoError, SeekStyle, SeekSet,
+              SeekCur, SeekEnd};
+
+use {PostgresResult, PostgresTransaction};
+use error::{PostgresError, PgStreamError, PgBadResponse};
+use types::{Oid, ToSql, FromSql};
+
+// Values from libpq/libpq-fs.h
+static INV_WRITE: i32 = 0x00020000;
+static INV_READ: i32 = 0x00040000;
+
+/// The access mode of an open large object
+pub enum LargeObjectMode {
+    /// The large object can only be read
+    Read,
+    /// The large object can only be written
+    Write,
+    /// The large object can be both read and written
+    ReadWrite,
+}
+
+fn query_one<T: FromSql>(trans: &PostgresTransaction, query: &str,
+                         params: &[&ToSql]) -> PostgresResult<T> {
+    let stmt = try!(trans.prepare(query));
+    let mut rows = try!(stmt.query(params));
+    let value = match rows.next() {
+        Some(row) => row.get(0u),
+        None => Err(PgBadResponse),
+    };
+    value
+}
+
+fn to_io_error(err: PostgresError) -> IoError {
+    match err {
+        PgStreamError(err) => err,
+        err => IoError {
+            kind: OtherIoError,
+            desc: "error accessing large object",
+            detail: Some(err.to_str()),
+        }
+    }
+}
+
+/// Creates a new, empty large object, returning its OID.
+pub fn create(trans: &PostgresTransaction) -> PostgresResult<Oid> {
+    let oid: i64 = try!(query_one(trans, "SELECT lo_create(0)::INT8", []));
+    Ok(oid as Oid)
+}
+
+/// Deletes a large object.
+pub fn unlink(trans: &PostgresTransaction, oid: Oid) -> PostgresResult<()> {
+    let oid = oid as i64;
+    let _: i32 = try!(query_one(trans, "SELECT lo_unlink($1::INT8::OID)", [&oid]));
+    Ok(())
+}
+
+/// Opens a large object.
+///
+/// The large object is closed when the returned `LargeObject` is dropped, or
+/// when the transaction ends.
+pub fn open<'a>(trans: &'a PostgresTransaction<'a>, oid: Oid,
+                mode: LargeObjectMode) -> PostgresResult<LargeObject<'a>> {
+    let mode = match mode {
+        Read => INV_READ,
+        Write => INV_WRITE,
+        ReadWrite => INV_READ | INV_WRITE,
+    };
+    let oid = oid as i64;
+    let fd: i32 = try!(query_one(trans, "SELECT lo_open($1::INT8::OID, $2)",
+                                 [&oid, &mode]));
+    Ok(LargeObject {
+        trans: trans,
+        fd: fd,
+        finished: false,
+    })
+}
+
+/// An open large object.
+///
+/// `LargeObject` implements `Reader`, `Writer` and `Seek`. Each call makes a
+/// round trip to the server, so it should be wrapped in a `BufferedReader`
+/// or `BufferedWriter` if it will be accessed with many small reads or
+/// writes.
+pub struct LargeObject<'a> {
+    trans: &'a PostgresTransaction<'a>,
+    fd: i32,
+    finished: bool,
+}
+
+#[unsafe_destructor]
+impl<'a> Drop for LargeObject<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.finish_inner();
+        }
+    }
+}
+
+impl<'a> LargeObject<'a> {
+    fn finish_inner(&mut self) -> PostgresResult<()> {
+        let _: i32 = try!(query_one(self.trans, "SELECT lo_close($1)", [&self.fd]));
+        Ok(())
+    }
+
+    /// Returns the large object descriptor used by the server for this
+    /// large object.
+    pub fn fd(&self) -> i32 {
+        self.fd
+    }
+
+    /// Truncates the large object to `len` bytes, extending it with zeroes
+    /// if it is shorter than that.
+    pub fn truncate(&mut self, len: i64) -> PostgresResult<()> {
+        let _: i32 = try!(query_one(self.trans, "SELECT lo_truncate64($1, $2)",
+                                    [&self.fd, &len]));
+        Ok(())
+    }
+
+    /// Consumes the `LargeObject`, closing it.
+    ///
+    /// Functionally identical to the `Drop` implementation of `LargeObject`
+    /// except that it returns any error to the caller.
+    pub fn finish(mut self) -> PostgresResult<()> {
+        self.finished = true;
+        self.finish_inner()
+    }
+}
+
+impl<'a> Reader for LargeObject<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        let len = buf.len() as i32;
+        let data: Vec<u8> = try!(query_one(self.trans, "SELECT loread($1, $2)",
+                                           [&self.fd, &len]).map_err(to_io_error));
+        if data.is_empty() && !buf.is_empty() {
+            return Err(IoError {
+                kind: EndOfFile,
+                desc: "end of large object",
+                detail: None,
+            });
+        }
+        buf.copy_from(data.as_slice());
+        Ok(data.len())
+    }
+}
+
+impl<'a> Writer for LargeObject<'a> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let _: i32 = try!(query_one(self.trans, "SELECT lowrite($1, $2)",
+                                    [&self.fd, &buf]).map_err(to_io_error));
+        Ok(())
+    }
+}
+
+impl<'a> Seek for LargeObject<'a> {
+    fn tell(&self) -> IoResult<u64> {
+        let pos: i64 = try!(query_one(self.trans, "SELECT lo_tell64($1)",
+                                      [&self.fd]).map_err(to_io_error));
+        Ok(pos as u64)
+    }
+
+    fn seek(&mut self, pos: i64, style: SeekStyle) -> IoResult<()> {
+        // Values from stdio.h
+        let whence = match style {
+            SeekSet => 0i32,
+            SeekCur => 1,
+            SeekEnd => 2,
+        };
+        let _: i64 = try!(query_one(self.trans, "SELECT lo_lseek64($1, $2, $3)",
+                                    [&self.fd, &pos, &whence]).map_err(to_io_error));
+        Ok(())
+    }
+}
@@ -75,25 +75,33 @@ extern crate uuid;
 use collections::{Deque, RingBuf};
 use url::{UserInfo, Url};
 use openssl::crypto::hash::{MD5, Hasher};
-use openssl::ssl::SslContext;
+use openssl::ssl::{SslContext, SslVerifyPeer};
 use serialize::hex::ToHex;
 use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{HashMap, LruCache};
 use std::from_str::FromStr;
-use std::io::{BufferedStream, IoResult};
+use std::io::{BufferedStream, BufReader, IoResult, IoError, MemReader, MemWriter, TimedOut};
 use std::io::net::ip::Port;
+use std::io::timer;
 use std::mem;
+use std::os;
+use std::sync::Arc;
 use std::task;
+use std::time::Duration;
 use std::fmt;
 
 use error::{InvalidUrl,
             MissingPassword,
+            InsecureCleartextPassword,
+            DisallowedAuthentication,
             MissingUser,
             PgConnectDbError,
             PgConnectStreamError,
             PgConnectBadResponse,
             PgDbError,
             PgInvalidColumn,
+            PgWrongColumnCount,
             PgStreamDesynchronized,
             PgStreamError,
             PgWrongParamCount,
@@ -102,20 +110,39 @@ use error::{InvalidUrl,
             PostgresError,
             UnsupportedAuthentication,
             PgWrongConnection,
+            PgStatementDeallocated,
+            PgEmptyInList,
             PgWrongTransaction,
-            PgBadResponse};
+            PgWrongType,
+            PgWrongParamType,
+            PgTransactionAborted,
+            PgBadData,
+            PgBadFieldLength,
+            PgBadResponse,
+            PgQueryTimeout,
+            PostgresSqlState,
+            QueryCanceled,
+            SerializationFailure,
+            DeadlockDetected};
 use io::{MaybeSslStream, InternalStream};
 use message::{AuthenticationCleartextPassword,
               AuthenticationGSS,
+              AuthenticationGSSContinue,
               AuthenticationKerberosV5,
               AuthenticationMD5Password,
               AuthenticationOk,
+              AuthenticationSASL,
+              AuthenticationSASLContinue,
+              AuthenticationSASLFinal,
               AuthenticationSCMCredential,
               AuthenticationSSPI,
+              AuthenticationUnknown,
               BackendKeyData,
               BackendMessage,
               BindComplete,
+              CloseComplete,
               CommandComplete,
+              CopyInResponse,
               DataRow,
               EmptyQueryResponse,
               ErrorResponse,
@@ -132,28 +159,51 @@ use message::{AuthenticationCleartextPassword,
 use message::{Bind,
               CancelRequest,
               Close,
+              CopyData,
+              CopyDone,
+              CopyFail,
               Describe,
               Execute,
+              Flush,
               FrontendMessage,
               Parse,
               PasswordMessage,
               Query,
+              SASLInitialResponse,
+              SASLResponse,
               StartupMessage,
               Sync,
               Terminate};
 use message::{WriteMessage, ReadMessage};
-use types::{Oid, PostgresType, ToSql, FromSql, PgUnknownType};
+use types::{Oid, PostgresType, ToSql, FromSql, PgUnknownType, PgUnknownArray, PgInt2, PgInt4,
+            PgInt8, PgBool, PgOid, PgFloat4, PgFloat8, PgText, PgVarchar, PgCharN, PgName,
+            PgXml, PgJson, PgJsonb, PgNumeric, PgByteA, PgUuid, PgDate, PgTime, PgTimeTz,
+            Format, Text, Binary};
+use types::date::Date;
+use types::numeric::Numeric;
+use types::time_of_day::{Time, TimeTz};
+use uuid::Uuid;
 
 #[macro_escape]
 mod macros;
 
 pub mod error;
 mod io;
+pub mod large_object;
 pub mod pool;
+pub mod query_builder;
 mod message;
+mod scram;
 pub mod types;
 
 static CANARY: u32 = 0xdeadbeef;
+static DEFAULT_MAX_MESSAGE_SIZE: uint = 1 << 30;
+// The usual operating system default, in seconds
+static DEFAULT_KEEPALIVE_IDLE: uint = 2 * 60 * 60;
+// The same as the default capacity of BufferedStream
+static DEFAULT_BUFFER_SIZE: uint = 64 * 1024;
+// The number of query descriptions kept by each connection
+static DESC_CACHE_CAPACITY: uint = 256;
 
 /// A typedef of the result returned by many methods.
 pub type PostgresResult<T> = Result<T, PostgresError>;
@@ -167,6 +217,49 @@ pub enum PostgresConnectTarget {
     TargetUnix(Path)
 }
 
+/// An authentication method which the server may use.
+///
+/// Used by `PostgresConnectParams::require_auth` to restrict the methods a
+/// connection will accept.
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum PostgresAuthMethod {
+    /// No authentication, as with the `trust` method in `pg_hba.conf`.
+    AuthNone,
+    /// A password sent in cleartext.
+    AuthPassword,
+    /// An MD5 hashed password.
+    AuthMd5,
+    /// SCRAM-SHA-256.
+    AuthScramSha256,
+}
+
+impl PostgresAuthMethod {
+    /// Returns the method with the specified name, as used by the
+    /// `require_auth` URL parameter.
+    ///
+    /// The names are `none`, `password`, `md5` and `scram-sha-256`, as in
+    /// libpq.
+    pub fn from_name(name: &str) -> Option<PostgresAuthMethod> {
+        match name {
+            "none" => Some(AuthNone),
+            "password" => Some(AuthPassword),
+            "md5" => Some(AuthMd5),
+            "scram-sha-256" => Some(AuthScramSha256),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the method.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            AuthNone => "none",
+            AuthPassword => "password",
+            AuthMd5 => "md5",
+            AuthScramSha256 => "scram-sha-256",
+        }
+    }
+}
+
 /// Information necessary to open a new connection to a Postgres server.
 #[deriving(Clone)]
 pub struct PostgresConnectParams {
@@ -187,6 +280,208 @@ pub struct PostgresConnectParams {
     pub database: Option<String>,
     /// Runtime parameters to be passed to the Postgres backend.
     pub options: Vec<(String, String)>,
+    /// The maximum amount of time to wait when opening the connection's
+    /// socket. If `None`, the attempt waits as long as the operating system
+    /// allows.
+    pub connect_timeout: Option<Duration>,
+    /// If set, TCP keepalives are enabled, with probes starting after the
+    /// connection has been idle for this many seconds.
+    pub keepalive: Option<uint>,
+    /// If true, the connection must use SSL. Connecting with `NoSsl` fails,
+    /// and `PreferSsl` behaves like `RequireSsl`.
+    pub require_ssl: bool,
+    /// If true, the `SslMode` must verify the server's certificate, i.e. be
+    /// `VerifyCa` or `VerifyFull`. Connecting with any other mode fails.
+    pub require_ssl_verify: bool,
+    /// If true, the `SslMode` must also verify that the server's certificate
+    /// was issued for the host, i.e. be `VerifyFull`.
+    pub require_ssl_hostname: bool,
+    /// The capacity in bytes of the buffer messages from the server are read
+    /// through. Defaults to 64 KiB if not specified.
+    pub read_buffer_size: Option<uint>,
+    /// The capacity in bytes of the buffer messages to the server are written
+    /// to. All messages of a request are buffered and sent together when it
+    /// is flushed, so a request larger than the buffer takes more than one
+    /// write. Defaults to 64 KiB if not specified.
+    pub write_buffer_size: Option<uint>,
+    /// If true, a password requested in cleartext is sent even if the
+    /// connection does not use SSL. Otherwise the connection fails with an
+    /// `InsecureCleartextPassword` error, since anyone able to observe the
+    /// traffic could read the password.
+    pub allow_insecure_cleartext: bool,
+    /// If set, the authentication methods the server may use. The connection
+    /// fails with a `DisallowedAuthentication` error if the server uses any
+    /// other method, before a password is sent.
+    ///
+    /// Including `AuthNone` permits the server to accept the connection
+    /// without authenticating it, which is otherwise rejected. This guards
+    /// against a `trust` line in `pg_hba.conf` letting the connection in
+    /// unauthenticated.
+    pub require_auth: Option<Vec<PostgresAuthMethod>>,
+}
+
+/// A builder for `PostgresConnectParams`.
+///
+/// Unlike a URL, values passed to the builder are never URL decoded, so a
+/// password containing characters like `@` or `/` can be used as-is.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use postgres::{PostgresConnection, PostgresConnectParamsBuilder, NoSsl};
+/// let params = PostgresConnectParamsBuilder::new()
+///     .host("localhost")
+///     .port(5432)
+///     .user("postgres")
+///     .password("p@ss/word")
+///     .database("foodb")
+///     .build();
+/// let maybe_conn = PostgresConnection::connect(params, &NoSsl);
+/// ```
+pub struct PostgresConnectParamsBuilder {
+    params: PostgresConnectParams,
+}
+
+impl PostgresConnectParamsBuilder {
+    /// Creates a new builder.
+    ///
+    /// The target defaults to TCP on `localhost` and the user defaults to the
+    /// value of the `USER` environment variable, if it is set.
+    pub fn new() -> PostgresConnectParamsBuilder {
+        PostgresConnectParamsBuilder {
+            params: PostgresConnectParams {
+                target: TargetTcp("localhost".to_str()),
+                port: None,
+                user: os::getenv("USER"),
+                password: None,
+                database: None,
+                options: vec![],
+                connect_timeout: None,
+                keepalive: None,
+                require_ssl: false,
+                require_ssl_verify: false,
+                require_ssl_hostname: false,
+                read_buffer_size: None,
+                write_buffer_size: None,
+                allow_insecure_cleartext: false,
+                require_auth: None,
+            }
+        }
+    }
+
+    /// Sets the host to connect to via TCP.
+    pub fn host<'a>(&'a mut self, host: &str)
+                    -> &'a mut PostgresConnectParamsBuilder {
+        self.params.target = TargetTcp(host.to_str());
+        self
+    }
+
+    /// Sets the directory containing the Unix socket to connect to.
+    pub fn unix_socket<'a>(&'a mut self, path: Path)
+                           -> &'a mut PostgresConnectParamsBuilder {
+        self.params.target = TargetUnix(path);
+        self
+    }
+
+    /// Sets the port to connect to.
+    pub fn port<'a>(&'a mut self, port: Port)
+                    -> &'a mut PostgresConnectParamsBuilder {
+        self.params.port = Some(port);
+        self
+    }
+
+    /// Sets the user to login as.
+    pub fn user<'a>(&'a mut self, user: &str)
+                    -> &'a mut PostgresConnectParamsBuilder {
+        self.params.user = Some(user.to_str());
+        self
+    }
+
+    /// Sets the password used for authentication.
+    pub fn password<'a>(&'a mut self, password: &str)
+                        -> &'a mut PostgresConnectParamsBuilder {
+        self.params.password = Some(password.to_str());
+        self
+    }
+
+    /// Sets the database to connect to.
+    pub fn database<'a>(&'a mut self, database: &str)
+                        -> &'a mut PostgresConnectParamsBuilder {
+        self.params.database = Some(database.to_str());
+        self
+    }
+
+    /// Adds a runtime parameter to be passed to the Postgres backend.
+    pub fn option<'a>(&'a mut self, name: &str, value: &str)
+                      -> &'a mut PostgresConnectParamsBuilder {
+        self.params.options.push((name.to_str(), value.to_str()));
+        self
+    }
+
+    /// Sets the maximum amount of time to wait when opening the connection.
+    pub fn connect_timeout<'a>(&'a mut self, timeout: Duration)
+                               -> &'a mut PostgresConnectParamsBuilder {
+        self.params.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Enables TCP keepalives, starting after the connection has been idle
+    /// for `idle_secs` seconds.
+    pub fn keepalive<'a>(&'a mut self, idle_secs: uint)
+                         -> &'a mut PostgresConnectParamsBuilder {
+        self.params.keepalive = Some(idle_secs);
+        self
+    }
+
+    /// Requires the connection to use SSL.
+    pub fn require_ssl<'a>(&'a mut self) -> &'a mut PostgresConnectParamsBuilder {
+        self.params.require_ssl = true;
+        self
+    }
+
+    /// Sets the capacity of the buffer used to read from the server.
+    ///
+    /// # Failure
+    ///
+    /// Fails if `size` is 0.
+    pub fn read_buffer_size<'a>(&'a mut self, size: uint)
+                                -> &'a mut PostgresConnectParamsBuilder {
+        assert!(size > 0, "read_buffer_size must be at least 1");
+        self.params.read_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the capacity of the buffer used to write to the server.
+    ///
+    /// # Failure
+    ///
+    /// Fails if `size` is 0.
+    pub fn write_buffer_size<'a>(&'a mut self, size: uint)
+                                 -> &'a mut PostgresConnectParamsBuilder {
+        assert!(size > 0, "write_buffer_size must be at least 1");
+        self.params.write_buffer_size = Some(size);
+        self
+    }
+
+    /// Allows a password requested in cleartext to be sent over a
+    /// connection which does not use SSL.
+    pub fn allow_insecure_cleartext<'a>(&'a mut self)
+                                        -> &'a mut PostgresConnectParamsBuilder {
+        self.params.allow_insecure_cleartext = true;
+        self
+    }
+
+    /// Sets the authentication methods the server may use.
+    pub fn require_auth<'a>(&'a mut self, methods: &[PostgresAuthMethod])
+                            -> &'a mut PostgresConnectParamsBuilder {
+        self.params.require_auth = Some(methods.to_vec());
+        self
+    }
+
+    /// Creates a `PostgresConnectParams` from the builder's configuration.
+    pub fn build(&self) -> PostgresConnectParams {
+        self.params.clone()
+    }
 }
 
 /// A trait implemented by types that can be converted into a
@@ -254,13 +549,83 @@ impl IntoConnectParams for Url {
             None
         };
 
+        // Parameters which configure the client rather than the server are
+        // pulled out of the runtime parameters
+        let mut connect_timeout = None;
+        let mut keepalives = None;
+        let mut keepalive_idle = None;
+        let mut require_ssl = false;
+        let mut require_ssl_verify = false;
+        let mut require_ssl_hostname = false;
+        let mut require_auth = None;
+        let mut runtime_options = vec![];
+        for (name, value) in options.move_iter() {
+            match name.as_slice() {
+                "connect_timeout" => match from_str::<i64>(value.as_slice()) {
+                    // As in libpq, a timeout of 0 means no timeout
+                    Some(secs) if secs > 0 => connect_timeout = Some(Duration::seconds(secs)),
+                    Some(_) => connect_timeout = None,
+                    None => return Err(InvalidUrl("invalid connect_timeout".to_str())),
+                },
+                "keepalives" => match value.as_slice() {
+                    "0" => keepalives = Some(false),
+                    "1" => keepalives = Some(true),
+                    _ => return Err(InvalidUrl("invalid keepalives".to_str())),
+                },
+                "keepalives_idle" => match from_str::<uint>(value.as_slice()) {
+                    Some(secs) => keepalive_idle = Some(secs),
+                    None => return Err(InvalidUrl("invalid keepalives_idle".to_str())),
+                },
+                "sslmode" => match value.as_slice() {
+                    "disable" | "allow" | "prefer" => require_ssl = false,
+                    "require" => require_ssl = true,
+                    "verify-ca" => {
+                        require_ssl = true;
+                        require_ssl_verify = true;
+                    }
+                    "verify-full" => {
+                        require_ssl = true;
+                        require_ssl_verify = true;
+                        require_ssl_hostname = true;
+                    }
+                    _ => return Err(InvalidUrl("invalid sslmode".to_str())),
+                },
+                "require_auth" => {
+                    let mut methods = vec![];
+                    for name in value.as_slice().split(',') {
+                        match PostgresAuthMethod::from_name(name) {
+                            Some(method) => methods.push(method),
+                            None => return Err(InvalidUrl("invalid require_auth".to_str())),
+                        }
+                    }
+                    require_auth = Some(methods);
+                }
+                _ => runtime_options.push((name, value)),
+            }
+        }
+        let keepalive = match (keepalives, keepalive_idle) {
+            (Some(false), _) => None,
+            (_, Some(secs)) => Some(secs),
+            (Some(true), None) => Some(DEFAULT_KEEPALIVE_IDLE),
+            (None, None) => None,
+        };
+
         Ok(PostgresConnectParams {
             target: target,
             port: port,
             user: user,
             password: pass,
             database: database,
-            options: options,
+            options: runtime_options,
+            connect_timeout: connect_timeout,
+            keepalive: keepalive,
+            require_ssl: require_ssl,
+            require_ssl_verify: require_ssl_verify,
+            require_ssl_hostname: require_ssl_hostname,
+            read_buffer_size: None,
+            write_buffer_size: None,
+            allow_insecure_cleartext: false,
+            require_auth: require_auth,
         })
     }
 }
@@ -310,6 +675,38 @@ impl<'conn> Iterator<PostgresNotification> for PostgresNotifications<'conn> {
     }
 }
 
+impl<'conn> PostgresNotifications<'conn> {
+    /// Returns the oldest pending notification, checking the socket for newly
+    /// arrived notifications if none have been received yet.
+    ///
+    /// Unlike `next`, this will pick up notifications sent while the
+    /// connection was idle. It never blocks waiting for a notification to
+    /// arrive, so it can be used to poll from an event loop.
+    pub fn next_if_available(&mut self)
+                             -> PostgresResult<Option<PostgresNotification>> {
+        check_desync!(self.conn);
+        let mut conn = self.conn.conn.borrow_mut();
+        match conn.notifications.pop_front() {
+            Some(notification) => return Ok(Some(notification)),
+            None => {}
+        }
+        try!(conn.read_pending_async_messages());
+        Ok(conn.notifications.pop_front())
+    }
+}
+
+/// The transaction status of a connection, as last reported by the server
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum PostgresTransactionStatus {
+    /// No transaction is in progress
+    Idle,
+    /// A transaction is in progress
+    InTransaction,
+    /// A transaction is in progress, but a statement in it has failed. Every
+    /// statement other than `ROLLBACK` will fail until the transaction ends.
+    InFailedTransaction,
+}
+
 /// Contains information necessary to cancel queries for a session
 pub struct PostgresCancelData {
     /// The process ID of the session
@@ -364,13 +761,254 @@ pub fn cancel_query<T: IntoConnectParams>(params: T, ssl: &SslMode,
     Ok(())
 }
 
+/// Expands the first occurrence of `marker` in `query` into a list of
+/// positional parameters, one for each of `values`, and appends `values` to
+/// `params`.
+///
+/// Postgres cannot bind a list of values to a single placeholder, so this
+/// allows an `IN` list of any length to be used. The new placeholders are
+/// numbered following the parameters already in `params`, so the number of
+/// placeholders in the returned query stays consistent with the final
+/// parameter list. If `marker` does not appear in `query`, the query is
+/// returned unchanged and `params` is not modified.
+///
+/// An empty `values` returns a `PgEmptyInList` error. `IN ()` is not valid
+/// syntax, and no substitute behaves correctly for both `IN` and `NOT IN`,
+/// so the caller must handle an empty list itself, usually by not running the
+/// query.
+///
+/// Since each distinct list length produces a distinct query, prefer binding
+/// an array with `= ANY($n)` for statements which are prepared once and
+/// executed many times.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use postgres::{PostgresConnection, NoSsl};
+/// # use postgres::types::ToSql;
+/// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+/// let name = "foo";
+/// let ids: &[&ToSql] = &[&1i32, &2i32, &3i32];
+/// let mut params: Vec<&ToSql> = vec![&name];
+/// let query = postgres::expand_in_list(
+///         "SELECT * FROM bar WHERE name = $1 AND id IN (?)", "?",
+///         &mut params, ids).unwrap();
+/// assert_eq!(query.as_slice(),
+///            "SELECT * FROM bar WHERE name = $1 AND id IN ($2, $3, $4)");
+/// let stmt = conn.prepare(query.as_slice()).unwrap();
+/// let rows = stmt.query(params.as_slice()).unwrap();
+/// ```
+pub fn expand_in_list<'a>(query: &str, marker: &str, params: &mut Vec<&'a ToSql>,
+                          values: &[&'a ToSql]) -> PostgresResult<String> {
+    let idx = match query.find_str(marker) {
+        Some(idx) => idx,
+        None => return Ok(query.to_str())
+    };
+
+    if values.is_empty() {
+        return Err(PgEmptyInList);
+    }
+    let placeholders: Vec<String> = range(0, values.len()).map(|i| {
+        format!("${}", params.len() + i + 1)
+    }).collect();
+    params.extend(values.iter().map(|value| *value));
+
+    Ok(format!("{}{}{}", query.slice_to(idx), placeholders.connect(", "),
+               query.slice_from(idx + marker.len())))
+}
+
+// Returns the OIDs of the types declared by `params`, with 0 for parameters
+// which leave their type to the server, or `None` if no parameter declares a
+// type.
+fn declared_param_types(params: &[&ToSql]) -> Option<Vec<Oid>> {
+    if params.iter().all(|param| param.declared_type().is_none()) {
+        return None;
+    }
+    Some(params.iter()
+        .map(|param| param.declared_type().map_or(0, |ty| ty.to_oid()))
+        .collect())
+}
+
+// Determines if a query is a ROLLBACK, which is the only kind of statement
+// allowed in a failed transaction.
+fn is_rollback(query: &str) -> bool {
+    let word = query.trim_left().split(|c: char| !c.is_alphabetic()).next().unwrap_or("");
+    ["ROLLBACK", "ABORT"].iter().any(|keyword| word.eq_ignore_ascii_case(*keyword))
+}
+
+// Determines if a command tag belongs to a command which may change the
+// description of a query, such as DDL or a change to search_path.
+fn invalidates_descriptions(tag: &str) -> bool {
+    let kind = tag.split(' ').next().unwrap_or("");
+    ["SET", "RESET", "DISCARD", "CREATE", "ALTER", "DROP", "ROLLBACK"].iter()
+        .any(|invalidating| *invalidating == kind)
+}
+
+// Returns the highest number used by a `$n` placeholder in `query`, skipping
+// over string literals, quoted identifiers and comments.
+fn highest_placeholder(query: &str) -> uint {
+    fn is_ident_char(c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || c == '$'
+    }
+
+    // Returns the index just past the first occurrence of `end` at or after
+    // `start`, or the end of `chars` if there is none.
+    fn skip_past(chars: &[char], start: uint, end: &[char]) -> uint {
+        let mut i = start;
+        while i + end.len() <= chars.len() {
+            if chars.slice(i, i + end.len()) == end {
+                return i + end.len();
+            }
+            i += 1;
+        }
+        chars.len()
+    }
+
+    let chars: Vec<char> = query.chars().collect();
+    let chars = chars.as_slice();
+    let len = chars.len();
+    let mut highest = 0;
+    // Set when the previous token was an `E` prefixing an escape string
+    let mut escapes = false;
+    let mut i = 0;
+    while i < len {
+        let c = chars[i];
+        // Identifiers may contain `$`, so `foo$1` is not a placeholder
+        if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < len && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            escapes = i - start == 1 && (c == 'E' || c == 'e');
+            continue;
+        }
+
+        let escape_string = escapes;
+        escapes = false;
+        match c {
+            '\'' => {
+                i += 1;
+                while i < len {
+                    match chars[i] {
+                        '\\' if escape_string => i += 2,
+                        '\'' if i + 1 < len && chars[i + 1] == '\'' => i += 2,
+                        '\'' => break,
+                        _ => i += 1,
+                    }
+                }
+                i += 1;
+            }
+            '"' => i = skip_past(chars, i + 1, ['"']),
+            '-' if i + 1 < len && chars[i + 1] == '-' => i = skip_past(chars, i + 2, ['\n']),
+            '/' if i + 1 < len && chars[i + 1] == '*' => {
+                // Block comments nest
+                let mut depth = 1u;
+                i += 2;
+                while i < len && depth > 0 {
+                    if chars[i] == '/' && i + 1 < len && chars[i + 1] == '*' {
+                        depth += 1;
+                        i += 2;
+                    } else if chars[i] == '*' && i + 1 < len && chars[i + 1] == '/' {
+                        depth -= 1;
+                        i += 2;
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            '$' => {
+                let mut j = i + 1;
+                let mut n = 0u;
+                loop {
+                    match if j < len { chars[j].to_digit(10) } else { None } {
+                        Some(digit) => {
+                            // Postgres allows at most 65535 parameters
+                            if n <= 65535 {
+                                n = n * 10 + digit;
+                            }
+                            j += 1;
+                        }
+                        None => break,
+                    }
+                }
+
+                if j > i + 1 {
+                    highest = cmp::max(highest, n);
+                    i = j;
+                } else {
+                    // A dollar quoted string, such as $$...$$ or $tag$...$tag$
+                    while j < len && (chars[j].is_alphanumeric() || chars[j] == '_') {
+                        j += 1;
+                    }
+                    if j < len && chars[j] == '$' {
+                        i = skip_past(chars, j + 1, chars.slice(i, j + 1));
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    highest
+}
+
+// Postgres treats every specified parameter type as declaring a parameter,
+// so types beyond the placeholders in the query would silently become extra
+// parameters.
+fn check_param_types(query: &str, param_types: &[Oid]) -> PostgresResult<()> {
+    if param_types.is_empty() {
+        return Ok(());
+    }
+    let expected = highest_placeholder(query);
+    if param_types.len() > expected {
+        return Err(PgWrongParamCount {
+            expected: expected,
+            actual: param_types.len(),
+        });
+    }
+    Ok(())
+}
+
+// Decodes a row of text format values, returning `None` if any of them is
+// not valid UTF-8.
+fn decode_text_row(row: Vec<Option<Vec<u8>>>) -> Option<Vec<Option<String>>> {
+    let mut values = Vec::with_capacity(row.len());
+    for value in row.move_iter() {
+        match value {
+            Some(buf) => match String::from_utf8(buf) {
+                Ok(value) => values.push(Some(value)),
+                Err(_) => return None,
+            },
+            None => values.push(None),
+        }
+    }
+    Some(values)
+}
+
+struct CachedDescription {
+    requested_types: Vec<Oid>,
+    param_types: Vec<PostgresType>,
+    result_desc: Arc<Vec<ResultDescription>>,
+}
+
 struct InnerPostgresConnection {
     stream: BufferedStream<MaybeSslStream<InternalStream>>,
     next_stmt_id: uint,
+    // Incremented by DEALLOCATE ALL, invalidating every existing statement
+    stmt_generation: uint,
     notice_handler: Box<PostgresNoticeHandler+Send>,
     notifications: RingBuf<PostgresNotification>,
     cancel_data: PostgresCancelData,
-    unknown_types: HashMap<Oid, String>,
+    cancel_params: PostgresConnectParams,
+    parameters: HashMap<String, String>,
+    transaction_status: PostgresTransactionStatus,
+    unknown_types: HashMap<Oid, PostgresType>,
+    desc_cache: LruCache<String, CachedDescription>,
+    max_message_size: uint,
+    // The start of a message which had only partly arrived when
+    // read_pending_async_messages returned
+    partial_message: Vec<u8>,
     desynchronized: bool,
     finished: bool,
     trans_depth: u32,
@@ -385,18 +1023,51 @@ impl Drop for InnerPostgresConnection {
     }
 }
 
+// Fails if `require_auth` does not allow the method the server is using
+fn check_auth_method(require_auth: &Option<Vec<PostgresAuthMethod>>,
+                     method: PostgresAuthMethod)
+                     -> Result<(), PostgresConnectError> {
+    match *require_auth {
+        Some(ref methods) if !methods.contains(&method) =>
+            Err(DisallowedAuthentication(method.name().to_str())),
+        _ => Ok(()),
+    }
+}
+
 impl InnerPostgresConnection {
     fn connect<T: IntoConnectParams>(params: T, ssl: &SslMode)
                                      -> Result<InnerPostgresConnection,
                                                PostgresConnectError> {
         let params = try!(params.into_connect_params());
         let stream = try!(io::initialize_stream(&params, ssl));
+        // Only the location of the server is needed to cancel queries
+        let cancel_params = PostgresConnectParams {
+            target: params.target.clone(),
+            port: params.port,
+            user: None,
+            password: None,
+            database: None,
+            options: vec![],
+            connect_timeout: params.connect_timeout,
+            keepalive: None,
+            require_ssl: false,
+            require_ssl_verify: false,
+            require_ssl_hostname: false,
+            read_buffer_size: None,
+            write_buffer_size: None,
+            allow_insecure_cleartext: false,
+            require_auth: None,
+        };
 
         let PostgresConnectParams {
             user,
             password,
             database,
             mut options,
+            read_buffer_size,
+            write_buffer_size,
+            allow_insecure_cleartext,
+            require_auth,
             ..
         } = params;
 
@@ -406,18 +1077,31 @@ impl InnerPostgresConnection {
         };
 
         let mut conn = InnerPostgresConnection {
-            stream: BufferedStream::new(stream),
+            stream: BufferedStream::with_capacities(
+                read_buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE),
+                write_buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE),
+                stream),
             next_stmt_id: 0,
+            stmt_generation: 0,
             notice_handler: box DefaultNoticeHandler,
             notifications: RingBuf::new(),
             cancel_data: PostgresCancelData { process_id: 0, secret_key: 0 },
+            cancel_params: cancel_params,
+            parameters: HashMap::new(),
+            transaction_status: Idle,
             unknown_types: HashMap::new(),
+            desc_cache: LruCache::new(DESC_CACHE_CAPACITY),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            partial_message: vec![],
             desynchronized: false,
             finished: false,
             trans_depth: 0,
             canary: CANARY,
         };
 
+        // Text values are decoded as UTF-8, so any client_encoding specified
+        // by the user is overridden.
+        options.retain(|&(ref name, _)| name.as_slice() != "client_encoding");
         options.push(("client_encoding".to_str(), "UTF8".to_str()));
         // Postgres uses the value of TimeZone as the time zone for TIMESTAMP
         // WITH TIME ZONE values. Timespec converts to GMT internally.
@@ -434,7 +1118,7 @@ impl InnerPostgresConnection {
             parameters: options.as_slice()
         }]));
 
-        try!(conn.handle_auth(user, password));
+        try!(conn.handle_auth(user, password, allow_insecure_cleartext, &require_auth));
 
         loop {
             match try_pg_conn!(conn.read_message()) {
@@ -461,39 +1145,193 @@ impl InnerPostgresConnection {
         Ok(try_desync!(self.stream.flush()))
     }
 
+    fn handle_async_message(&mut self, message: BackendMessage)
+                            -> Option<BackendMessage> {
+        match message {
+            NoticeResponse { fields } =>
+                self.notice_handler.handle(PostgresDbError::new(fields)),
+            NotificationResponse { pid, channel, payload } =>
+                self.notifications.push_back(PostgresNotification {
+                    pid: pid,
+                    channel: channel,
+                    payload: payload
+                }),
+            ParameterStatus { parameter, value } => {
+                debug!("Parameter {} = {}", parameter, value);
+                self.parameters.insert(parameter, value);
+            }
+            val => return Some(val)
+        }
+        None
+    }
+
+    // Returns the number of bytes missing from partial_message, or 0 once it
+    // holds a whole message. An invalid length counts as complete, so that
+    // decoding the message reports it.
+    fn partial_message_remaining(&self) -> uint {
+        let buf = self.partial_message.as_slice();
+        if buf.len() < 5 {
+            return 5 - buf.len();
+        }
+        let len = BufReader::new(buf.slice(1, 5)).read_be_i32().unwrap();
+        if len < 4 || len as uint - 4 > self.max_message_size {
+            return 0;
+        }
+        1 + len as uint - buf.len()
+    }
+
+    // Reads the next message from the stream, first completing any message
+    // whose start was received by read_pending_async_messages.
+    fn read_stream_message(&mut self) -> IoResult<BackendMessage> {
+        if self.partial_message.is_empty() {
+            return self.stream.read_message_limited(self.max_message_size);
+        }
+        loop {
+            let remaining = self.partial_message_remaining();
+            if remaining == 0 {
+                break;
+            }
+            let buf = try!(self.stream.read_exact(remaining));
+            self.partial_message.push_all(buf.as_slice());
+        }
+        let buf = mem::replace(&mut self.partial_message, vec![]);
+        MemReader::new(buf).read_message_limited(self.max_message_size)
+    }
+
     fn read_message(&mut self) -> IoResult<BackendMessage> {
         assert!(!self.desynchronized);
         loop {
-            match try_desync!(self.stream.read_message()) {
-                NoticeResponse { fields } =>
-                    self.notice_handler.handle(PostgresDbError::new(fields)),
-                NotificationResponse { pid, channel, payload } =>
-                    self.notifications.push_back(PostgresNotification {
-                        pid: pid,
-                        channel: channel,
-                        payload: payload
-                    }),
-                ParameterStatus { parameter, value } =>
-                    debug!("Parameter {} = {}", parameter, value),
-                val => return Ok(val)
+            let message = try_desync!(self.read_stream_message());
+            match self.handle_async_message(message) {
+                Some(message) => {
+                    match message {
+                        ReadyForQuery { state } => self.set_transaction_status(state),
+                        CommandComplete { ref tag }
+                                if invalidates_descriptions(tag.as_slice()) =>
+                            self.desc_cache.clear(),
+                        _ => {}
+                    }
+                    return Ok(message);
+                }
+                None => {}
+            }
+        }
+    }
+
+    // Processes any asynchronous messages which have already arrived without
+    // blocking. Must only be called when no query is in progress, since any
+    // other message is a protocol violation at that point.
+    fn read_pending_async_messages(&mut self) -> PostgresResult<()> {
+        loop {
+            // Only the bytes already received are taken, and only up to the
+            // end of the current message, so a message split across packets
+            // is kept in partial_message until the rest of it arrives.
+            let remaining = self.partial_message_remaining();
+            if remaining > 0 {
+                self.stream.get_mut_ref().set_read_timeout(Some(0));
+                let read = match self.stream.fill_buf() {
+                    Ok(buf) => {
+                        let len = cmp::min(remaining, buf.len());
+                        self.partial_message.push_all(buf.slice_to(len));
+                        Ok(len)
+                    }
+                    Err(err) => Err(err)
+                };
+                self.stream.get_mut_ref().set_read_timeout(None);
+
+                match read {
+                    Ok(0) | Err(IoError { kind: TimedOut, .. }) => return Ok(()),
+                    Ok(len) => self.stream.consume(len),
+                    Err(err) => {
+                        self.desynchronized = true;
+                        return Err(PgStreamError(err));
+                    }
+                }
+                continue;
+            }
+
+            let message = match self.read_stream_message() {
+                Ok(message) => message,
+                Err(err) => {
+                    self.desynchronized = true;
+                    return Err(PgStreamError(err));
+                }
+            };
+            match self.handle_async_message(message) {
+                Some(_) => {
+                    self.desynchronized = true;
+                    return Err(PgBadResponse);
+                }
+                None => {}
+            }
+        }
+    }
+
+    // Blocks until a notification arrives. Must only be called when no query
+    // is in progress.
+    fn wait_for_notification(&mut self) -> PostgresResult<PostgresNotification> {
+        check_desync!(self);
+        loop {
+            match self.notifications.pop_front() {
+                Some(notification) => return Ok(notification),
+                None => {}
             }
+
+            let message = match self.read_stream_message() {
+                Ok(message) => message,
+                Err(err) => {
+                    self.desynchronized = true;
+                    return Err(PgStreamError(err));
+                }
+            };
+            match self.handle_async_message(message) {
+                Some(_) => {
+                    self.desynchronized = true;
+                    return Err(PgBadResponse);
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn set_transaction_status(&mut self, state: u8) {
+        self.transaction_status = match state as char {
+            'T' => InTransaction,
+            'E' => InFailedTransaction,
+            _ => Idle,
+        };
+    }
+
+    // Statements other than ROLLBACK are rejected by the server in a failed
+    // transaction, so fail early with a clearer error.
+    fn check_not_aborted(&self) -> PostgresResult<()> {
+        match self.transaction_status {
+            InFailedTransaction => Err(PgTransactionAborted),
+            _ => Ok(()),
         }
     }
 
-    fn handle_auth(&mut self, user: String, pass: Option<String>)
+    fn handle_auth(&mut self, user: String, pass: Option<String>,
+                   allow_insecure_cleartext: bool,
+                   require_auth: &Option<Vec<PostgresAuthMethod>>)
             -> Result<(), PostgresConnectError> {
         match try_pg_conn!(self.read_message()) {
-            AuthenticationOk => return Ok(()),
+            AuthenticationOk => return check_auth_method(require_auth, AuthNone),
             AuthenticationCleartextPassword => {
+                try!(check_auth_method(require_auth, AuthPassword));
                 let pass = match pass {
                     Some(pass) => pass,
                     None => return Err(MissingPassword)
                 };
+                if !self.stream.get_ref().is_encrypted() && !allow_insecure_cleartext {
+                    return Err(InsecureCleartextPassword);
+                }
                 try_pg_conn!(self.write_messages([PasswordMessage {
                         password: pass.as_slice(),
                     }]));
             }
             AuthenticationMD5Password { salt } => {
+                try!(check_auth_method(require_auth, AuthMd5));
                 let pass = match pass {
                     Some(pass) => pass,
                     None => return Err(MissingPassword)
@@ -511,10 +1349,59 @@ impl InnerPostgresConnection {
                         password: output.as_slice()
                     }]));
             }
-            AuthenticationKerberosV5
-            | AuthenticationSCMCredential
-            | AuthenticationGSS
-            | AuthenticationSSPI => return Err(UnsupportedAuthentication),
+            AuthenticationSASL { mechanisms } => {
+                try!(check_auth_method(require_auth, AuthScramSha256));
+                let pass = match pass {
+                    Some(pass) => pass,
+                    None => return Err(MissingPassword)
+                };
+                if !mechanisms.iter().any(|m| m.as_slice() == scram::SCRAM_SHA_256) {
+                    let method = format!("SASL ({})", mechanisms.connect(", "));
+                    return Err(UnsupportedAuthentication(method));
+                }
+                let mut scram = scram::ScramSha256::new(pass.as_slice());
+                try_pg_conn!(self.write_messages([SASLInitialResponse {
+                        mechanism: scram::SCRAM_SHA_256,
+                        data: scram.message()
+                    }]));
+
+                let data = match try_pg_conn!(self.read_message()) {
+                    AuthenticationSASLContinue { data } => data,
+                    ErrorResponse { fields } =>
+                        return Err(PgConnectDbError(PostgresDbError::new(fields))),
+                    _ => {
+                        self.desynchronized = true;
+                        return Err(PgConnectBadResponse);
+                    }
+                };
+                try!(scram.update(data.as_slice()));
+                try_pg_conn!(self.write_messages([SASLResponse {
+                        data: scram.message()
+                    }]));
+
+                let data = match try_pg_conn!(self.read_message()) {
+                    AuthenticationSASLFinal { data } => data,
+                    ErrorResponse { fields } =>
+                        return Err(PgConnectDbError(PostgresDbError::new(fields))),
+                    _ => {
+                        self.desynchronized = true;
+                        return Err(PgConnectBadResponse);
+                    }
+                };
+                try!(scram.finish(data.as_slice()));
+            }
+            AuthenticationKerberosV5 =>
+                return Err(UnsupportedAuthentication("Kerberos V5".to_str())),
+            AuthenticationSCMCredential =>
+                return Err(UnsupportedAuthentication("SCM credential".to_str())),
+            AuthenticationGSS | AuthenticationGSSContinue =>
+                return Err(UnsupportedAuthentication("GSSAPI".to_str())),
+            AuthenticationSSPI =>
+                return Err(UnsupportedAuthentication("SSPI".to_str())),
+            AuthenticationUnknown { code } => {
+                let method = format!("unknown (code {})", code);
+                return Err(UnsupportedAuthentication(method));
+            }
             ErrorResponse { fields } =>
                 return Err(PgConnectDbError(PostgresDbError::new(fields))),
             _ => {
@@ -541,6 +1428,14 @@ impl InnerPostgresConnection {
 
     fn prepare<'a>(&mut self, query: &str, conn: &'a PostgresConnection)
             -> PostgresResult<PostgresStatement<'a>> {
+        self.prepare_typed(query, [], conn)
+    }
+
+    fn describe<'a>(&mut self, query: &str, conn: &'a PostgresConnection)
+            -> PostgresResult<PostgresDescribedStatement<'a>> {
+        if !is_rollback(query) {
+            try!(self.check_not_aborted());
+        }
         let stmt_name = format!("s{}", self.next_stmt_id);
         self.next_stmt_id += 1;
 
@@ -554,74 +1449,289 @@ impl InnerPostgresConnection {
                 variant: 'S' as u8,
                 name: stmt_name.as_slice(),
             },
-            Sync]));
+            Flush]));
 
         match try_pg!(self.read_message()) {
             ParseComplete => {}
             ErrorResponse { fields } => {
+                try_pg!(self.write_messages([Sync]));
                 try!(self.wait_for_ready());
                 return Err(PgDbError(PostgresDbError::new(fields)));
             }
             _ => bad_response!(),
         }
 
-        let mut param_types: Vec<PostgresType> = match try_pg!(self.read_message()) {
+        let param_types = match try_pg!(self.read_message()) {
             ParameterDescription { types } =>
                 types.iter().map(|ty| PostgresType::from_oid(*ty)).collect(),
             _ => bad_response!(),
         };
 
-        let mut result_desc: Vec<ResultDescription> = match try_pg!(self.read_message()) {
+        let result_desc = match try_pg!(self.read_message()) {
             RowDescription { descriptions } =>
                 descriptions.move_iter().map(|desc| {
                     let RowDescriptionEntry { name, type_oid, .. } = desc;
+                    let ty = PostgresType::from_oid(type_oid);
                     ResultDescription {
                         name: name,
-                        ty: PostgresType::from_oid(type_oid)
+                        format: ty.result_format(),
+                        ty: ty
                     }
                 }).collect(),
             NoData => vec![],
             _ => bad_response!()
         };
 
+        Ok(PostgresDescribedStatement {
+            stmt: PostgresStatement {
+                conn: conn,
+                name: stmt_name,
+                param_types: param_types,
+                result_desc: Arc::new(result_desc),
+                next_portal_id: Cell::new(0),
+                finished: false,
+                deallocated: Cell::new(false),
+                generation: self.stmt_generation,
+                deferred_parse: None,
+                parsed: Cell::new(true),
+                rollback: is_rollback(query),
+            }
+        })
+    }
+
+    fn prepare_no_describe<'a>(&mut self, query: &str, param_types: &[Oid],
+                               result_types: &[Oid], conn: &'a PostgresConnection)
+            -> PostgresResult<PostgresStatement<'a>> {
+        if !is_rollback(query) {
+            try!(self.check_not_aborted());
+        }
+        try!(check_param_types(query, param_types));
+        let stmt_name = format!("s{}", self.next_stmt_id);
+        self.next_stmt_id += 1;
+
+        try_pg!(self.write_messages([
+            Parse {
+                name: stmt_name.as_slice(),
+                query: query,
+                param_types: param_types
+            },
+            Sync]));
+
+        match try_pg!(self.read_message()) {
+            ParseComplete => {}
+            ErrorResponse { fields } => {
+                try!(self.wait_for_ready());
+                return Err(PgDbError(PostgresDbError::new(fields)));
+            }
+            _ => bad_response!(),
+        }
         try!(self.wait_for_ready());
 
-        // now that the connection is ready again, get unknown type names
-        try!(self.set_type_names(param_types.mut_iter()));
-        try!(self.set_type_names(result_desc.mut_iter().map(|d| &mut d.ty)));
+        let param_types = param_types.iter().map(|ty| PostgresType::from_oid(*ty)).collect();
+        let result_desc = result_types.iter().map(|ty| {
+            let ty = PostgresType::from_oid(*ty);
+            ResultDescription {
+                name: String::new(),
+                format: ty.result_format(),
+                ty: ty
+            }
+        }).collect();
 
         Ok(PostgresStatement {
             conn: conn,
             name: stmt_name,
             param_types: param_types,
-            result_desc: result_desc,
+            result_desc: Arc::new(result_desc),
             next_portal_id: Cell::new(0),
             finished: false,
+            deallocated: Cell::new(false),
+            generation: self.stmt_generation,
+            deferred_parse: None,
+            parsed: Cell::new(true),
+            rollback: is_rollback(query),
         })
     }
 
-    fn set_type_names<'a, I: Iterator<&'a mut PostgresType>>(&mut self, mut it: I)
-            -> PostgresResult<()> {
-        for ty in it {
-            match *ty {
-                PgUnknownType { oid, ref mut name } =>
-                    *name = try!(self.get_type_name(oid)),
-                _ => {}
-            }
+    fn prepare_typed<'a>(&mut self, query: &str, param_types: &[Oid],
+                         conn: &'a PostgresConnection)
+            -> PostgresResult<PostgresStatement<'a>> {
+        if !is_rollback(query) {
+            try!(self.check_not_aborted());
         }
-        Ok(())
-    }
+        try!(check_param_types(query, param_types));
+        let stmt_name = format!("s{}", self.next_stmt_id);
+        self.next_stmt_id += 1;
 
-    fn get_type_name(&mut self, oid: Oid) -> PostgresResult<String> {
-        match self.unknown_types.find(&oid) {
-            Some(name) => return Ok(name.clone()),
+        let cached = match self.desc_cache.get(&query.to_str()) {
+            Some(desc) if desc.requested_types.as_slice() == param_types =>
+                Some((desc.param_types.clone(), desc.result_desc.clone())),
+            _ => None
+        };
+        match cached {
+            // The Parse is sent along with the statement's first execution,
+            // so a cache hit doesn't contact the server at all
+            Some((cached_types, cached_desc)) => {
+                return Ok(PostgresStatement {
+                    conn: conn,
+                    name: stmt_name,
+                    param_types: cached_types,
+                    result_desc: cached_desc,
+                    next_portal_id: Cell::new(0),
+                    finished: false,
+                    deallocated: Cell::new(false),
+                    generation: self.stmt_generation,
+                    deferred_parse: Some((query.to_str(), Vec::from_slice(param_types))),
+                    parsed: Cell::new(false),
+                    rollback: is_rollback(query),
+                });
+            }
+            None => {}
+        }
+
+        let requested_types = Vec::from_slice(param_types);
+        try_pg!(self.write_messages([
+            Parse {
+                name: stmt_name.as_slice(),
+                query: query,
+                param_types: param_types
+            },
+            Describe {
+                variant: 'S' as u8,
+                name: stmt_name.as_slice(),
+            },
+            Sync]));
+
+        match try_pg!(self.read_message()) {
+            ParseComplete => {}
+            ErrorResponse { fields } => {
+                try!(self.wait_for_ready());
+                return Err(PgDbError(PostgresDbError::new(fields)));
+            }
+            _ => bad_response!(),
+        }
+
+        let mut param_types: Vec<PostgresType> = match try_pg!(self.read_message()) {
+            ParameterDescription { types } =>
+                types.iter().map(|ty| PostgresType::from_oid(*ty)).collect(),
+            _ => bad_response!(),
+        };
+
+        let mut result_desc: Vec<ResultDescription> = match try_pg!(self.read_message()) {
+            RowDescription { descriptions } =>
+                descriptions.move_iter().map(|desc| {
+                    let RowDescriptionEntry { name, type_oid, .. } = desc;
+                    let ty = PostgresType::from_oid(type_oid);
+                    ResultDescription {
+                        name: name,
+                        format: ty.result_format(),
+                        ty: ty
+                    }
+                }).collect(),
+            NoData => vec![],
+            _ => bad_response!()
+        };
+
+        try!(self.wait_for_ready());
+
+        // now that the connection is ready again, get unknown type names
+        let names = match self.set_type_names(param_types.mut_iter()) {
+            Ok(()) => self.set_type_names(result_desc.mut_iter().map(|d| &mut d.ty)),
+            Err(err) => Err(err),
+        };
+        match names {
+            Ok(()) => {}
+            Err(err) => {
+                // There's no PostgresStatement to close the statement when
+                // it's dropped, so it has to be closed here
+                let _ = self.close_statement(stmt_name.as_slice());
+                return Err(err);
+            }
+        }
+        set_result_formats(result_desc.as_mut_slice());
+
+        let result_desc = Arc::new(result_desc);
+        self.desc_cache.put(query.to_str(), CachedDescription {
+            requested_types: requested_types,
+            param_types: param_types.clone(),
+            result_desc: result_desc.clone(),
+        });
+
+        Ok(PostgresStatement {
+            conn: conn,
+            name: stmt_name,
+            param_types: param_types,
+            result_desc: result_desc,
+            next_portal_id: Cell::new(0),
+            finished: false,
+            deallocated: Cell::new(false),
+            generation: self.stmt_generation,
+            deferred_parse: None,
+            parsed: Cell::new(true),
+            rollback: is_rollback(query),
+        })
+    }
+
+    fn close_statement(&mut self, name: &str) -> PostgresResult<()> {
+        check_desync!(self);
+        try_pg!(self.write_messages([
+            Close {
+                variant: 'S' as u8,
+                name: name
+            },
+            Sync]));
+        loop {
+            match try_pg!(self.read_message()) {
+                ReadyForQuery { .. } => break,
+                ErrorResponse { fields } => {
+                    try!(self.wait_for_ready());
+                    return Err(PgDbError(PostgresDbError::new(fields)));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn set_type_names<'a, I: Iterator<&'a mut PostgresType>>(&mut self, mut it: I)
+            -> PostgresResult<()> {
+        for ty in it {
+            let oid = match *ty {
+                PgUnknownType { oid, .. } => oid,
+                _ => continue
+            };
+            *ty = try!(self.get_unknown_type(oid));
+        }
+        Ok(())
+    }
+
+    // Array types are resolved to PgUnknownArray so their values can be
+    // converted element by element with array_to_sql and array_from_sql.
+    fn get_unknown_type(&mut self, oid: Oid) -> PostgresResult<PostgresType> {
+        match self.unknown_types.find(&oid) {
+            Some(ty) => return Ok(ty.clone()),
             None => {}
         }
-        let name = try!(self.quick_query(format!("SELECT typname FROM pg_type \
-                                                  WHERE oid={}", oid).as_slice()))
-            .move_iter().next().unwrap().move_iter().next().unwrap().unwrap();
-        self.unknown_types.insert(oid, name.clone());
-        Ok(name)
+        let row = try!(self.quick_query(format!("SELECT t.typname, e.oid, e.typname \
+                                                 FROM pg_type t \
+                                                 LEFT JOIN pg_type e \
+                                                 ON t.typcategory = 'A' AND e.oid = t.typelem \
+                                                 WHERE t.oid={}", oid).as_slice()))
+            .move_iter().next().unwrap();
+        let mut row = row.move_iter();
+        let name = row.next().unwrap().unwrap();
+        let ty = match (row.next().unwrap(), row.next().unwrap()) {
+            (Some(member_oid), Some(member_name)) => {
+                let member_oid = from_str(member_oid.as_slice()).unwrap();
+                let member = match PostgresType::from_oid(member_oid) {
+                    PgUnknownType { oid, .. } => PgUnknownType { name: member_name, oid: oid },
+                    member => member
+                };
+                PgUnknownArray { name: name, oid: oid, member: box member }
+            }
+            _ => PgUnknownType { name: name, oid: oid }
+        };
+        self.unknown_types.insert(oid, ty.clone());
+        Ok(ty)
     }
 
     fn is_desynchronized(&self) -> bool {
@@ -645,13 +1755,52 @@ impl InnerPostgresConnection {
         try_pg!(self.write_messages([Query { query: query }]));
 
         let mut result = vec![];
+        // The rest of the response must still be read if a value is invalid
+        let mut bad_data = false;
         loop {
             match try_pg!(self.read_message()) {
                 ReadyForQuery { .. } => break,
-                DataRow { row } => {
-                    result.push(row.move_iter().map(|opt| {
-                        opt.map(|b| String::from_utf8(b).unwrap())
-                    }).collect());
+                DataRow { row } => match decode_text_row(row) {
+                    Some(row) => result.push(row),
+                    None => bad_data = true,
+                },
+                ErrorResponse { fields } => {
+                    try!(self.wait_for_ready());
+                    return Err(PgDbError(PostgresDbError::new(fields)));
+                }
+                _ => {}
+            }
+        }
+        if bad_data {
+            return Err(PgBadData);
+        }
+        Ok(result)
+    }
+
+    fn simple_query(&mut self, query: &str)
+            -> PostgresResult<Vec<SimpleQueryMessage>> {
+        check_desync!(self);
+        if !is_rollback(query) {
+            try!(self.check_not_aborted());
+        }
+        try_pg!(self.write_messages([Query { query: query }]));
+
+        let mut result = vec![];
+        // The rest of the response must still be read if a value is invalid
+        let mut bad_data = false;
+        loop {
+            match try_pg!(self.read_message()) {
+                ReadyForQuery { .. } => break,
+                DataRow { row } => match decode_text_row(row) {
+                    Some(row) => result.push(SimpleQueryRow(row)),
+                    None => bad_data = true,
+                },
+                CommandComplete { tag } =>
+                    result.push(SimpleQueryComplete(CommandTag::parse(tag.as_slice()))),
+                CopyInResponse { .. } => {
+                    try_pg!(self.write_messages([CopyFail {
+                        message: "COPY FROM STDIN is not supported by simple_query",
+                    }]));
                 }
                 ErrorResponse { fields } => {
                     try!(self.wait_for_ready());
@@ -660,6 +1809,9 @@ impl InnerPostgresConnection {
                 _ => {}
             }
         }
+        if bad_data {
+            return Err(PgBadData);
+        }
         Ok(result)
     }
 
@@ -689,6 +1841,30 @@ impl PostgresConnection {
     /// (5432) is used if none is specified. The database name defaults to the
     /// username if not specified.
     ///
+    /// The query parameters are passed to the server as runtime parameters,
+    /// such as `application_name`, except for the following, which configure
+    /// the client as they do in libpq:
+    ///
+    /// * `connect_timeout` - the number of seconds to wait when opening the
+    ///     connection. 0 means to wait indefinitely.
+    /// * `keepalives` - 1 to enable TCP keepalives, 0 to disable them.
+    /// * `keepalives_idle` - the number of idle seconds after which TCP
+    ///     keepalives are sent. Specifying it enables keepalives unless
+    ///     `keepalives=0` is also given; otherwise, keepalives enabled with
+    ///     `keepalives=1` start after two hours.
+    /// * `sslmode` - `require`, `verify-ca` and `verify-full` require the
+    ///     connection to use SSL, as if `RequireSsl` was used in place of
+    ///     `PreferSsl`. `verify-ca` additionally requires the `SslMode` to be
+    ///     `VerifyCa` or `VerifyFull`, and `verify-full` requires it to be
+    ///     `VerifyFull`; otherwise `MissingSslVerification` is returned.
+    ///     `disable`, `allow` and `prefer` leave the `SslMode` in charge.
+    ///
+    /// The `trust`, `password`, `md5`, and `scram-sha-256` authentication
+    /// methods are supported. If the server requests a password and none was
+    /// provided, `MissingPassword` is returned. If the server rejects the
+    /// password, a `PgConnectDbError` with the `InvalidPassword` code is
+    /// returned.
+    ///
     /// To connect to the server via Unix sockets, `host` should be set to the
     /// absolute path of the directory containing the socket file. Since `/` is
     /// a reserved character in URLs, the path should be URL encoded.  If the
@@ -724,6 +1900,15 @@ impl PostgresConnection {
     ///     password: None,
     ///     database: None,
     ///     options: vec![],
+    ///     connect_timeout: None,
+    ///     keepalive: None,
+    ///     require_ssl: false,
+    ///     require_ssl_verify: false,
+    ///     require_ssl_hostname: false,
+    ///     read_buffer_size: None,
+    ///     write_buffer_size: None,
+    ///     allow_insecure_cleartext: false,
+    ///     require_auth: None,
     /// };
     /// let maybe_conn = PostgresConnection::connect(params, &NoSsl);
     /// ```
@@ -735,6 +1920,54 @@ impl PostgresConnection {
         })
     }
 
+    /// Sets the maximum size in bytes of a message the connection will accept
+    /// from the server.
+    ///
+    /// A message whose header declares a larger size causes an error, and
+    /// desynchronizes the connection, rather than an attempt to allocate space
+    /// for it. The default is 1 GiB, the largest size of a single field value
+    /// the server supports.
+    pub fn set_max_message_size(&self, size: uint) {
+        self.conn.borrow_mut().max_message_size = size;
+    }
+
+    /// Returns the maximum size in bytes of a message the connection will
+    /// accept from the server.
+    pub fn max_message_size(&self) -> uint {
+        self.conn.borrow().max_message_size
+    }
+
+    /// Returns the current value of a run-time parameter reported by the
+    /// server, or `None` if the server has not reported the parameter.
+    ///
+    /// The server reports a fixed set of parameters, including
+    /// `server_version`, `server_encoding`, `client_encoding`, `DateStyle`,
+    /// `TimeZone`, `integer_datetimes` and `application_name`, and sends the
+    /// new value whenever one of them changes.
+    ///
+    /// `client_encoding` is always requested as `UTF8` when connecting, since
+    /// text values are decoded as UTF-8. Values which are not valid UTF-8
+    /// produce a `PgBadData` error when they are read.
+    pub fn parameter(&self, param: &str) -> Option<String> {
+        self.conn.borrow().parameters.find_equiv(&param).map(|value| value.clone())
+    }
+
+    /// Processes any asynchronous messages which have already arrived from
+    /// the server, without blocking or sending a query.
+    ///
+    /// Notifications are queued to be returned by `notifications`, notices
+    /// are passed to the notice handler, and parameter changes are reflected
+    /// by `parameter`. This is analogous to libpq's `PQconsumeInput`, and is
+    /// useful when the connection's socket is being polled for readability.
+    ///
+    /// Only complete messages are processed. The start of a message which has
+    /// only partly arrived is kept until a later call, or the next query,
+    /// receives the rest of it, so this never waits for the server.
+    pub fn consume_input(&self) -> PostgresResult<()> {
+        check_desync!(self);
+        self.conn.borrow_mut().read_pending_async_messages()
+    }
+
     /// Sets the notice handler for the connection, returning the old handler.
     pub fn set_notice_handler(&self, handler: Box<PostgresNoticeHandler+Send>)
             -> Box<PostgresNoticeHandler+Send> {
@@ -750,6 +1983,50 @@ impl PostgresConnection {
         }
     }
 
+    /// Consumes the connection, returning a channel over which asynchronous
+    /// notifications will be delivered as they arrive.
+    ///
+    /// A connection cannot run queries while it is blocked waiting for
+    /// notifications, so a background task takes ownership of this connection
+    /// for the rest of its lifetime. A dedicated connection should be used for
+    /// this purpose, and any `LISTEN` commands must be executed on it before
+    /// calling this method. Notifications which have already been received
+    /// are delivered first.
+    ///
+    /// The channel is closed if the connection is lost or the server sends an
+    /// unexpected message. If the `Receiver` is dropped, the background task
+    /// will shut down and close the connection when the next notification
+    /// arrives.
+    pub fn spawn_notification_listener(self) -> Receiver<PostgresNotification> {
+        let (tx, rx) = channel();
+        task::spawn(proc() {
+            let mut conn = self.conn.borrow_mut();
+            loop {
+                let notification = match conn.wait_for_notification() {
+                    Ok(notification) => notification,
+                    Err(err) => {
+                        debug!("Notification listener exiting: {}", err);
+                        break;
+                    }
+                };
+                if tx.send_opt(notification).is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    /// Creates a new pipeline of statement executions.
+    ///
+    /// See the documentation of `PostgresPipeline` for details.
+    pub fn pipeline<'a>(&'a self) -> PostgresPipeline<'a> {
+        PostgresPipeline {
+            conn: self,
+            entries: vec![],
+        }
+    }
+
     /// Creates a new prepared statement.
     ///
     /// A statement may contain parameters, specified by `$n` where `n` is the
@@ -759,6 +2036,18 @@ impl PostgresConnection {
     /// The statement is associated with the connection that created it and may
     /// not outlive that connection.
     ///
+    /// The parameter and column descriptions of the most recently prepared
+    /// queries are cached by the connection. Preparing a cached query again
+    /// does not contact the server: the statement is parsed along with its
+    /// first execution, and the `Describe` request and any lookups of unknown
+    /// type names are skipped entirely. As a result, an error in such a query
+    /// is reported when it is first executed rather than by `prepare`.
+    ///
+    /// The cache is cleared whenever the connection runs a `SET`, `RESET`,
+    /// `DISCARD`, `ROLLBACK` or DDL command. It is not aware of changes made
+    /// by other connections, so call `clear_describe_cache` after altering
+    /// anything a prepared query refers to from elsewhere.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -778,6 +2067,157 @@ impl PostgresConnection {
         conn.prepare(query, self)
     }
 
+    /// Like `prepare`, but with the types of some or all of the statement's
+    /// parameters specified by OID.
+    ///
+    /// The `n`th OID specifies the type of the parameter `$n`. Any parameters
+    /// not covered by the list, or given an OID of 0, have their types
+    /// inferred by the server as usual. This can be used to force a parameter
+    /// to be interpreted as a specific type when inference would pick the
+    /// wrong one.
+    ///
+    /// Specifying more OIDs than the query has parameters returns a
+    /// `PgWrongParamCount` error, since Postgres would otherwise treat each
+    /// extra OID as declaring an extra parameter.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// // 25 is the OID of TEXT
+    /// let stmt = conn.prepare_typed("SELECT $1 || 'foo'", [25]).unwrap();
+    /// ```
+    pub fn prepare_typed<'a>(&'a self, query: &str, param_types: &[Oid])
+            -> PostgresResult<PostgresStatement<'a>> {
+        let mut conn = self.conn.borrow_mut();
+        if conn.trans_depth != 0 {
+            return Err(PgWrongTransaction);
+        }
+        conn.prepare_typed(query, param_types, self)
+    }
+
+    /// Like `prepare_typed`, but without asking the server to describe the
+    /// statement.
+    ///
+    /// Preparing a statement normally takes a round trip to fetch the types
+    /// of its parameters and columns. This method instead trusts the caller
+    /// to provide them: `param_types` must list the OID of every parameter,
+    /// and `result_types` the OID of every column returned by the statement.
+    /// This saves the `Describe` request, but the resulting statement only
+    /// knows what it was told. Columns have no names, so they can only be
+    /// accessed by index, and the names of types unknown to this library are
+    /// not looked up. Statements prepared this way are not added to the
+    /// describe cache.
+    ///
+    /// If the provided types are wrong, nothing is detected until the
+    /// statement is executed. The server rejects parameter values which don't
+    /// match the declared types, and an execution fails with a server error
+    /// if the number of result types differs from the number of columns,
+    /// unless no result types were given at all. Columns whose actual type
+    /// differs from the declared one may fail to convert with `PgBadData`,
+    /// or may convert to nonsense values.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// // 23 is the OID of INT4 and 25 is the OID of TEXT
+    /// let stmt = conn.prepare_no_describe("SELECT name FROM people WHERE id = $1",
+    ///                                     [23], [25]).unwrap();
+    /// ```
+    pub fn prepare_no_describe<'a>(&'a self, query: &str, param_types: &[Oid],
+                                   result_types: &[Oid])
+            -> PostgresResult<PostgresStatement<'a>> {
+        let mut conn = self.conn.borrow_mut();
+        if conn.trans_depth != 0 {
+            return Err(PgWrongTransaction);
+        }
+        conn.prepare_no_describe(query, param_types, result_types, self)
+    }
+
+    /// Starts a `COPY ... FROM STDIN` operation.
+    ///
+    /// The data written to the returned `PostgresCopyIn` is sent to the
+    /// server as it is written, and the operation completes when it is
+    /// finished. See `BinaryCopyWriter` for a way to write typed rows in the
+    /// binary `COPY` format.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let mut copy = conn.copy_in("COPY foo (id, name) FROM STDIN").unwrap();
+    /// copy.write(b"1\tjohn\n2\tjane\n").unwrap();
+    /// let rows = copy.finish().unwrap();
+    /// ```
+    pub fn copy_in<'a>(&'a self, query: &str)
+            -> PostgresResult<PostgresCopyIn<'a>> {
+        if self.conn.borrow().trans_depth != 0 {
+            return Err(PgWrongTransaction);
+        }
+        self.copy_in_inner(query)
+    }
+
+    fn copy_in_inner<'a>(&'a self, query: &str)
+            -> PostgresResult<PostgresCopyIn<'a>> {
+        check_desync!(self);
+        try!(self.conn.borrow().check_not_aborted());
+        try_pg!(self.write_messages([Query { query: query }]));
+
+        loop {
+            match try_pg!(self.read_message()) {
+                CopyInResponse { .. } => break,
+                ErrorResponse { fields } => {
+                    try!(self.wait_for_ready());
+                    return Err(PgDbError(PostgresDbError::new(fields)));
+                }
+                // the query was not a COPY FROM STDIN
+                ReadyForQuery { .. } => return Err(PgBadResponse),
+                _ => {}
+            }
+        }
+
+        Ok(PostgresCopyIn {
+            conn: self,
+            finished: false,
+        })
+    }
+
+    /// Parses and describes a statement without completing the protocol
+    /// exchange.
+    ///
+    /// The server is asked to `Flush` rather than `Sync` after describing the
+    /// statement, so its parameter and column types can be inspected before
+    /// deciding whether to execute it, all without ending the implicit
+    /// transaction the exchange runs in. See `PostgresDescribedStatement` for
+    /// details.
+    ///
+    /// The connection must not be used for anything else until the returned
+    /// object has been executed, finished, or dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # use postgres::types::PgInt4;
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.describe("DELETE FROM foo WHERE id = $1").unwrap();
+    /// if stmt.param_types() == &[PgInt4] {
+    ///     stmt.execute([&1i32]).unwrap();
+    /// }
+    /// ```
+    pub fn describe<'a>(&'a self, query: &str)
+            -> PostgresResult<PostgresDescribedStatement<'a>> {
+        let mut conn = self.conn.borrow_mut();
+        if conn.trans_depth != 0 {
+            return Err(PgWrongTransaction);
+        }
+        conn.describe(query, self)
+    }
+
     /// Begins a new transaction.
     ///
     /// Returns a `PostgresTransaction` object which should be used instead of
@@ -786,6 +2226,12 @@ impl PostgresConnection {
     /// A transaction will commit by default unless the task fails or the
     /// transaction is set to roll back.
     ///
+    /// The connection tracks how deeply transactions are nested. While a
+    /// transaction is active, calling this method again returns
+    /// `PgWrongTransaction` rather than sending a second `BEGIN`, which the
+    /// server would only warn about. Use `PostgresTransaction::transaction` to
+    /// nest a transaction inside of the active one as a savepoint.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -820,22 +2266,103 @@ impl PostgresConnection {
         })
     }
 
+    /// Runs a closure inside of a transaction, retrying it if the transaction
+    /// fails because of a serialization failure or a deadlock.
+    ///
+    /// The closure is called with a new transaction on each attempt. If it
+    /// returns `Ok`, the transaction is committed. If the closure or the
+    /// commit fails with a `SerializationFailure` or `DeadlockDetected` error,
+    /// the transaction is rolled back and the closure is called again, up to
+    /// a total of `max_attempts` times. Any other error is returned
+    /// immediately, as is the error from the final attempt. The closure may
+    /// therefore run more than once, and should have no effects outside of
+    /// the transaction.
+    ///
+    /// If `backoff` is specified, the connection sleeps for `backoff` times
+    /// the number of failed attempts before each retry.
+    ///
+    /// # Failure
+    ///
+    /// Fails if `max_attempts` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let result = conn.transaction_retry(5, None, |trans| {
+    ///     try!(trans.execute("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE", []));
+    ///     trans.execute("UPDATE foo SET bar = bar + 1", [])
+    /// });
+    /// ```
+    pub fn transaction_retry<T>(&self, max_attempts: uint, backoff: Option<Duration>,
+                                f: |&PostgresTransaction| -> PostgresResult<T>)
+                                -> PostgresResult<T> {
+        assert!(max_attempts > 0, "max_attempts must be at least 1");
+        let mut attempt = 1;
+        loop {
+            let trans = try!(self.transaction());
+            let result = match f(&trans) {
+                Ok(value) => trans.finish().map(|_| value),
+                Err(err) => {
+                    trans.set_rollback();
+                    let _ = trans.finish();
+                    Err(err)
+                }
+            };
+
+            match result {
+                Err(PgDbError(ref err))
+                    if attempt < max_attempts && is_retryable(&err.code) => {}
+                result => return result,
+            }
+
+            match backoff {
+                Some(backoff) => {
+                    let ms = (backoff * attempt as i32).num_milliseconds();
+                    timer::sleep(cmp::max(ms, 0) as u64);
+                }
+                None => {}
+            }
+            attempt += 1;
+        }
+    }
+
     /// A convenience function for queries that are only run once.
     ///
     /// If an error is returned, it could have come from either the preparation
     /// or execution of the statement.
     ///
     /// On success, returns the number of rows modified or 0 if not applicable.
+    ///
+    /// The types of any parameters which declare one, like `types::Null`, are
+    /// specified when the query is prepared.
     pub fn execute(&self, query: &str, params: &[&ToSql])
             -> PostgresResult<uint> {
-        self.prepare(query).and_then(|stmt| stmt.execute(params))
+        self.prepare_for_params(query, params).and_then(|stmt| stmt.execute(params))
     }
 
-    /// Execute a sequence of SQL statements.
-    ///
-    /// Statements should be separated by `;` characters. If an error occurs,
-    /// execution of the sequence will stop at that point. This is intended for
-    /// execution of batches of non-dynamic statements - for example, creation
+    /// Like `execute`, but returns the full command tag reported by the
+    /// server rather than just the number of rows modified.
+    pub fn execute_returning_tag(&self, query: &str, params: &[&ToSql])
+            -> PostgresResult<CommandTag> {
+        self.prepare_for_params(query, params)
+            .and_then(|stmt| stmt.execute_returning_tag(params))
+    }
+
+    fn prepare_for_params<'a>(&'a self, query: &str, params: &[&ToSql])
+            -> PostgresResult<PostgresStatement<'a>> {
+        match declared_param_types(params) {
+            Some(types) => self.prepare_typed(query, types.as_slice()),
+            None => self.prepare(query),
+        }
+    }
+
+    /// Execute a sequence of SQL statements.
+    ///
+    /// Statements should be separated by `;` characters. If an error occurs,
+    /// execution of the sequence will stop at that point. This is intended for
+    /// execution of batches of non-dynamic statements - for example, creation
     /// of a schema for a fresh database.
     ///
     /// # Warning
@@ -872,9 +2399,136 @@ impl PostgresConnection {
         if conn.trans_depth != 0 {
             return Err(PgWrongTransaction);
         }
+        if !is_rollback(query) {
+            try!(conn.check_not_aborted());
+        }
         conn.quick_query(query).map(|_| ())
     }
 
+    /// Returns the transaction status of the connection, as reported by the
+    /// server at the end of the last query.
+    ///
+    /// If a statement fails inside of a transaction, the status becomes
+    /// `InFailedTransaction` and every statement other than `ROLLBACK` (or
+    /// `ROLLBACK TO SAVEPOINT`) will return a `PgTransactionAborted` error
+    /// until the transaction ends. Finishing a `PostgresTransaction` in this
+    /// state rolls it back.
+    pub fn transaction_status(&self) -> PostgresTransactionStatus {
+        self.conn.borrow().transaction_status
+    }
+
+    /// Executes a sequence of SQL statements with the simple query protocol,
+    /// returning the rows and command tags they produce.
+    ///
+    /// Unlike `batch_execute`, the rows returned by the statements are
+    /// collected. The messages are returned in the order the server sent
+    /// them: each statement's rows are followed by its command tag. The
+    /// simple query protocol always uses the text format, so values are
+    /// returned as strings, with `None` representing `NULL`.
+    ///
+    /// If a statement fails, execution stops at that point and the error is
+    /// returned. Unless an explicit transaction is in progress, the
+    /// statements run in a single implicit transaction, so the effects of
+    /// earlier statements are rolled back as well.
+    ///
+    /// The same warning about user-specified data as for `batch_execute`
+    /// applies.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl, SimpleQueryRow};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// for message in conn.simple_query("SHOW TimeZone").unwrap().move_iter() {
+    ///     match message {
+    ///         SimpleQueryRow(row) => println!("{}", row),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn simple_query(&self, query: &str)
+            -> PostgresResult<Vec<SimpleQueryMessage>> {
+        let mut conn = self.conn.borrow_mut();
+        if conn.trans_depth != 0 {
+            return Err(PgWrongTransaction);
+        }
+        conn.simple_query(query)
+    }
+
+    /// Runs a closure with the `statement_timeout` parameter set to the
+    /// specified number of milliseconds.
+    ///
+    /// The timeout is enforced by the server: a statement running for longer
+    /// than the timeout is canceled, and returns a `PgDbError` with the
+    /// `QueryCanceled` code. `statement_timeout` is reset once the closure
+    /// returns, even if it returns an error or fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let result = conn.with_statement_timeout(5000, || {
+    ///     conn.execute("UPDATE foo SET bar = 1", [])
+    /// });
+    /// ```
+    pub fn with_statement_timeout<T>(&self, timeout_ms: uint,
+                                     f: || -> PostgresResult<T>)
+                                     -> PostgresResult<T> {
+        let query = format!("SET statement_timeout = {}", timeout_ms);
+        try!(self.quick_query(query.as_slice()));
+        let guard = SettingGuard {
+            conn: self,
+            reset: "RESET statement_timeout".to_str(),
+            finished: false,
+        };
+        guard.run(f)
+    }
+
+    /// Runs a closure with the `search_path` parameter set to the specified
+    /// list of schemas.
+    ///
+    /// Each schema name is quoted, so names are matched exactly and may
+    /// contain any characters. The previous `search_path` is restored once
+    /// the closure returns, even if it returns an error or fails. The describe
+    /// cache is cleared both when the `search_path` is set and when it is
+    /// restored, since a query may refer to different objects under each.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let result = conn.with_search_path(["tenant_42", "public"], || {
+    ///     conn.execute("UPDATE foo SET bar = 1", [])
+    /// });
+    /// ```
+    pub fn with_search_path<T>(&self, schemas: &[&str],
+                               f: || -> PostgresResult<T>)
+                               -> PostgresResult<T> {
+        let previous = try!(self.quick_query("SHOW search_path"));
+        let previous = match previous.as_slice() {
+            [ref row] if row.len() == 1 => match row[0] {
+                Some(ref previous) => previous.clone(),
+                None => return Err(PgBadResponse),
+            },
+            _ => return Err(PgBadResponse),
+        };
+
+        let path = schemas.iter()
+            .map(|schema| quote_identifier(*schema))
+            .collect::<Vec<String>>()
+            .connect(", ");
+        try!(self.quick_query(set_search_path_query(path.as_slice()).as_slice()));
+        self.clear_describe_cache();
+        let guard = SettingGuard {
+            conn: self,
+            reset: set_search_path_query(previous.as_slice()),
+            finished: false,
+        };
+        guard.run(f)
+    }
+
     /// Returns information used to cancel pending queries.
     ///
     /// Used with the `cancel_query` function. The object returned can be used
@@ -892,11 +2546,47 @@ impl PostgresConnection {
         self.conn.borrow().is_desynchronized()
     }
 
+    /// Clears the connection's cache of statement descriptions.
+    ///
+    /// See `prepare` for details.
+    pub fn clear_describe_cache(&self) {
+        self.conn.borrow_mut().desc_cache.clear();
+    }
+
+    /// Closes a prepared statement on the server without consuming it.
+    ///
+    /// Any further attempt to execute `stmt` returns a
+    /// `PgStatementDeallocated` error, and dropping it no longer contacts the
+    /// server. Deallocating a statement a second time does nothing.
+    pub fn deallocate(&self, stmt: &PostgresStatement) -> PostgresResult<()> {
+        if self as *const _ != stmt.conn as *const _ {
+            return Err(PgWrongConnection);
+        }
+        if stmt.is_deallocated() {
+            return Ok(());
+        }
+        stmt.deallocated.set(true);
+        self.conn.borrow_mut().close_statement(stmt.name.as_slice())
+    }
+
+    /// Closes every prepared statement in the session with `DEALLOCATE ALL`.
+    ///
+    /// All existing `PostgresStatement`s created from this connection behave
+    /// as if they had been passed to `deallocate`. Statements prepared
+    /// afterwards are unaffected.
+    pub fn deallocate_all(&self) -> PostgresResult<()> {
+        try!(self.quick_query("DEALLOCATE ALL"));
+        self.conn.borrow_mut().stmt_generation += 1;
+        Ok(())
+    }
+
     /// Consumes the connection, closing it.
     ///
     /// Functionally equivalent to the `Drop` implementation for
     /// `PostgresConnection` except that it returns any error encountered to
-    /// the caller.
+    /// the caller. The `Terminate` message is written and flushed before this
+    /// method returns, so an `Ok` result indicates that the server has been
+    /// told to end the session.
     pub fn finish(self) -> PostgresResult<()> {
         let mut conn = self.conn.borrow_mut();
         conn.finished = true;
@@ -923,6 +2613,17 @@ impl PostgresConnection {
     fn write_messages(&self, messages: &[FrontendMessage]) -> IoResult<()> {
         self.conn.borrow_mut().write_messages(messages)
     }
+
+    // Aborts a COPY FROM STDIN started by a statement executed with the
+    // extended protocol. The server ignores the Sync sent after the Execute
+    // while it is copying, so another is needed to end the exchange.
+    fn abort_copy_in(&self) -> IoResult<()> {
+        self.write_messages([
+            CopyFail {
+                message: "COPY FROM STDIN is only supported by copy_in",
+            },
+            Sync])
+    }
 }
 
 /// Specifies the SSL support requested for a new connection
@@ -932,7 +2633,41 @@ pub enum SslMode {
     /// The connection will use SSL if the backend supports it
     PreferSsl(SslContext),
     /// The connection must use SSL
-    RequireSsl(SslContext)
+    RequireSsl(SslContext),
+    /// The connection must use SSL, and the server's certificate chain must
+    /// be valid.
+    ///
+    /// This corresponds to libpq's `verify-ca` mode.
+    VerifyCa(VerifyingSslContext),
+    /// Like `VerifyCa`, but the server's certificate must additionally have
+    /// been issued for the host being connected to.
+    ///
+    /// The host is checked against the certificate's common name. A name may
+    /// contain a wildcard as its entire leftmost label, which matches exactly
+    /// one label of the host. This corresponds to libpq's `verify-full` mode.
+    VerifyFull(VerifyingSslContext)
+}
+
+/// An `SslContext` which verifies the server's certificate chain.
+///
+/// Used by the `VerifyCa` and `VerifyFull` SSL modes, so that they can't be
+/// given a context which would accept any certificate.
+pub struct VerifyingSslContext {
+    ctx: SslContext,
+}
+
+impl VerifyingSslContext {
+    /// Wraps a context, enabling verification of its peer.
+    ///
+    /// Any verification mode or callback previously set on the context is
+    /// replaced. The trusted certificate authorities must still be configured,
+    /// for example with `set_CA_file`.
+    pub fn new(mut ctx: SslContext) -> VerifyingSslContext {
+        ctx.set_verify(SslVerifyPeer, None);
+        VerifyingSslContext {
+            ctx: ctx,
+        }
+    }
 }
 
 /// Represents a transaction on a database connection.
@@ -945,6 +2680,50 @@ pub struct PostgresTransaction<'conn> {
     finished: bool,
 }
 
+// Restores a configuration parameter changed for the duration of a closure,
+// even if the closure fails. The describe cache is cleared as well, since the
+// parameter may have changed how queries are described.
+struct SettingGuard<'conn> {
+    conn: &'conn PostgresConnection,
+    reset: String,
+    finished: bool,
+}
+
+#[unsafe_destructor]
+impl<'conn> Drop for SettingGuard<'conn> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.reset();
+        }
+    }
+}
+
+impl<'conn> SettingGuard<'conn> {
+    fn run<T>(mut self, f: || -> PostgresResult<T>) -> PostgresResult<T> {
+        let result = f();
+        self.finished = true;
+        let reset = self.reset();
+        result.and_then(|result| reset.map(|_| result))
+    }
+
+    fn reset(&self) -> PostgresResult<()> {
+        let result = self.conn.quick_query(self.reset.as_slice());
+        self.conn.clear_describe_cache();
+        result.map(|_| ())
+    }
+}
+
+fn quote_identifier(ident: &str) -> String {
+    format!("\"{}\"", ident.replace("\"", "\"\""))
+}
+
+// The value is passed as an escape string literal so that it is interpreted
+// the same way whatever standard_conforming_strings is set to.
+fn set_search_path_query(path: &str) -> String {
+    let path = path.replace("\\", "\\\\").replace("'", "''");
+    format!("SELECT pg_catalog.set_config('search_path', E'{}', false)", path)
+}
+
 #[unsafe_destructor]
 impl<'conn> Drop for PostgresTransaction<'conn> {
     fn drop(&mut self) {
@@ -957,7 +2736,9 @@ impl<'conn> Drop for PostgresTransaction<'conn> {
 impl<'conn> PostgresTransaction<'conn> {
     fn finish_inner(&mut self) -> PostgresResult<()> {
         debug_assert!(self.depth == self.conn.conn.borrow().trans_depth);
-        let rollback = task::failing() || !self.commit.get();
+        // A failed transaction can only be rolled back
+        let aborted = self.conn.transaction_status() == InFailedTransaction;
+        let rollback = task::failing() || !self.commit.get() || aborted;
         let query = match (rollback, self.depth != 1) {
             (true, true) => "ROLLBACK TO sp",
             (true, false) => "ROLLBACK",
@@ -965,22 +2746,84 @@ impl<'conn> PostgresTransaction<'conn> {
             (false, false) => "COMMIT",
         };
         self.conn.conn.borrow_mut().trans_depth -= 1;
-        self.conn.quick_query(query).map(|_| ())
+        try!(self.conn.quick_query(query));
+        if aborted && self.commit.get() {
+            Err(PgTransactionAborted)
+        } else {
+            Ok(())
+        }
     }
 
     /// Like `PostgresConnection::prepare`.
-    pub fn prepare<'a>(&'a self, query: &str)
-            -> PostgresResult<PostgresStatement<'a>> {
+    ///
+    /// The statement is bound to the connection rather than the transaction,
+    /// so it can still be used after the transaction ends.
+    pub fn prepare(&self, query: &str)
+            -> PostgresResult<PostgresStatement<'conn>> {
         if self.conn.conn.borrow().trans_depth != self.depth {
             return Err(PgWrongTransaction);
         }
         self.conn.conn.borrow_mut().prepare(query, self.conn)
     }
 
+    /// Like `PostgresConnection::prepare_typed`.
+    pub fn prepare_typed(&self, query: &str, param_types: &[Oid])
+            -> PostgresResult<PostgresStatement<'conn>> {
+        if self.conn.conn.borrow().trans_depth != self.depth {
+            return Err(PgWrongTransaction);
+        }
+        self.conn.conn.borrow_mut().prepare_typed(query, param_types,
+                                                  self.conn)
+    }
+
+    /// Like `PostgresConnection::prepare_no_describe`.
+    pub fn prepare_no_describe(&self, query: &str, param_types: &[Oid],
+                               result_types: &[Oid])
+            -> PostgresResult<PostgresStatement<'conn>> {
+        if self.conn.conn.borrow().trans_depth != self.depth {
+            return Err(PgWrongTransaction);
+        }
+        self.conn.conn.borrow_mut().prepare_no_describe(query, param_types,
+                                                        result_types, self.conn)
+    }
+
+    /// Like `PostgresConnection::describe`.
+    pub fn describe<'a>(&'a self, query: &str)
+            -> PostgresResult<PostgresDescribedStatement<'a>> {
+        if self.conn.conn.borrow().trans_depth != self.depth {
+            return Err(PgWrongTransaction);
+        }
+        self.conn.conn.borrow_mut().describe(query, self.conn)
+    }
+
+    /// Like `PostgresConnection::copy_in`.
+    pub fn copy_in<'a>(&'a self, query: &str)
+            -> PostgresResult<PostgresCopyIn<'a>> {
+        if self.conn.conn.borrow().trans_depth != self.depth {
+            return Err(PgWrongTransaction);
+        }
+        self.conn.copy_in_inner(query)
+    }
+
     /// Like `PostgresConnection::execute`.
     pub fn execute(&self, query: &str, params: &[&ToSql])
             -> PostgresResult<uint> {
-        self.prepare(query).and_then(|s| s.execute(params))
+        self.prepare_for_params(query, params).and_then(|s| s.execute(params))
+    }
+
+    /// Like `PostgresConnection::execute_returning_tag`.
+    pub fn execute_returning_tag(&self, query: &str, params: &[&ToSql])
+            -> PostgresResult<CommandTag> {
+        self.prepare_for_params(query, params)
+            .and_then(|s| s.execute_returning_tag(params))
+    }
+
+    fn prepare_for_params<'a>(&'a self, query: &str, params: &[&ToSql])
+            -> PostgresResult<PostgresStatement<'a>> {
+        match declared_param_types(params) {
+            Some(types) => self.prepare_typed(query, types.as_slice()),
+            None => self.prepare(query),
+        }
     }
 
     /// Like `PostgresConnection::batch_execute`.
@@ -991,6 +2834,16 @@ impl<'conn> PostgresTransaction<'conn> {
         self.conn.batch_execute(query)
     }
 
+    /// Like `PostgresConnection::simple_query`.
+    pub fn simple_query(&self, query: &str)
+            -> PostgresResult<Vec<SimpleQueryMessage>> {
+        let mut conn = self.conn.conn.borrow_mut();
+        if conn.trans_depth != self.depth {
+            return Err(PgWrongTransaction);
+        }
+        conn.simple_query(query)
+    }
+
     /// Like `PostgresConnection::transaction`.
     pub fn transaction<'a>(&'a self)
             -> PostgresResult<PostgresTransaction<'a>> {
@@ -1032,6 +2885,22 @@ impl<'conn> PostgresTransaction<'conn> {
         self.finish_inner()
     }
 
+    /// Consumes the transaction, committing it.
+    ///
+    /// Any error reported by the server is returned, such as a violation of
+    /// a deferred constraint which is only checked at commit time. In that
+    /// case the transaction has been rolled back.
+    pub fn commit(self) -> PostgresResult<()> {
+        self.set_commit();
+        self.finish()
+    }
+
+    /// Consumes the transaction, rolling it back.
+    pub fn rollback(self) -> PostgresResult<()> {
+        self.set_rollback();
+        self.finish()
+    }
+
     /// Executes a prepared statement, returning a lazily loaded iterator over
     /// the resulting rows.
     ///
@@ -1059,13 +2928,29 @@ impl<'conn> PostgresTransaction<'conn> {
 }
 
 /// A prepared statement
+///
+/// Prepared statements belong to the connection's session rather than to a
+/// transaction. A statement remains valid until it is dropped, and may be
+/// executed inside or outside of any number of transactions on the
+/// connection which prepared it, including after the transaction it was
+/// prepared in has committed or rolled back.
 pub struct PostgresStatement<'conn> {
     conn: &'conn PostgresConnection,
     name: String,
     param_types: Vec<PostgresType>,
-    result_desc: Vec<ResultDescription>,
+    result_desc: Arc<Vec<ResultDescription>>,
     next_portal_id: Cell<uint>,
     finished: bool,
+    deallocated: Cell<bool>,
+    generation: uint,
+    // The query and parameter types of a statement prepared from a cached
+    // description, which is parsed on the server along with its first
+    // execution
+    deferred_parse: Option<(String, Vec<Oid>)>,
+    parsed: Cell<bool>,
+    // Whether the statement is a ROLLBACK, which may run in a failed
+    // transaction
+    rollback: bool,
 }
 
 #[unsafe_destructor]
@@ -1079,28 +2964,47 @@ impl<'conn> Drop for PostgresStatement<'conn> {
 
 impl<'conn> PostgresStatement<'conn> {
     fn finish_inner(&mut self) -> PostgresResult<()> {
-        check_desync!(self.conn);
-        try_pg!(self.conn.write_messages([
-            Close {
-                variant: 'S' as u8,
-                name: self.name.as_slice()
-            },
-            Sync]));
-        loop {
-            match try_pg!(self.conn.read_message()) {
-                ReadyForQuery { .. } => break,
-                ErrorResponse { fields } => {
-                    try!(self.conn.wait_for_ready());
-                    return Err(PgDbError(PostgresDbError::new(fields)));
-                }
-                _ => {}
+        if self.is_deallocated() || !self.parsed.get() {
+            return Ok(());
+        }
+        self.conn.conn.borrow_mut().close_statement(self.name.as_slice())
+    }
+
+    // Returns the Parse message of a deferred statement if it has not been
+    // parsed yet. The caller must send it before the statement's first Bind,
+    // and mark the statement as parsed once the server sends ParseComplete.
+    fn deferred_parse<'a>(&'a self) -> Option<FrontendMessage<'a>> {
+        if self.parsed.get() {
+            return None;
+        }
+        self.deferred_parse.as_ref().map(|&(ref query, ref param_types)| {
+            Parse {
+                name: self.name.as_slice(),
+                query: query.as_slice(),
+                param_types: param_types.as_slice()
             }
+        })
+    }
+
+    fn check_not_aborted(&self) -> PostgresResult<()> {
+        if self.rollback {
+            return Ok(());
         }
-        Ok(())
+        self.conn.conn.borrow().check_not_aborted()
     }
 
-    fn inner_execute(&self, portal_name: &str, row_limit: i32, params: &[&ToSql])
-            -> PostgresResult<()> {
+    fn is_deallocated(&self) -> bool {
+        self.deallocated.get()
+            || self.generation != self.conn.conn.borrow().stmt_generation
+    }
+
+    fn encode_params(&self, params: &[&ToSql])
+            -> PostgresResult<(Vec<i16>, Vec<Option<Vec<u8>>>)> {
+        if self.is_deallocated() {
+            return Err(PgStatementDeallocated);
+        }
+        // Every Bind goes through here, so extra parameters are rejected even
+        // for statements without any, rather than being dropped by the zip
         if self.param_types.len() != params.len() {
             return Err(PgWrongParamCount {
                 expected: self.param_types.len(),
@@ -1109,29 +3013,61 @@ impl<'conn> PostgresStatement<'conn> {
         }
         let mut formats = vec![];
         let mut values = vec![];
-        for (param, ty) in params.iter().zip(self.param_types.iter()) {
-            let (format, value) = try!(param.to_sql(ty));
+        for (i, (param, ty)) in params.iter().zip(self.param_types.iter()).enumerate() {
+            let (format, value) = match param.to_sql(ty) {
+                Ok(ok) => ok,
+                Err(PgWrongType(_)) =>
+                    return Err(PgWrongParamType { index: i + 1, ty: ty.clone() }),
+                Err(err) => return Err(err),
+            };
             formats.push(format as i16);
             values.push(value);
         };
+        Ok((formats, values))
+    }
 
-        let result_formats: Vec<i16> = self.result_desc.iter().map(|desc| {
-            desc.ty.result_format() as i16
-        }).collect();
+    fn result_formats(&self) -> Vec<i16> {
+        self.result_desc.iter().map(|desc| desc.format as i16).collect()
+    }
 
-        try_pg!(self.conn.write_messages([
-            Bind {
-                portal: portal_name,
-                statement: self.name.as_slice(),
-                formats: formats.as_slice(),
-                values: values.as_slice(),
-                result_formats: result_formats.as_slice()
-            },
-            Execute {
-                portal: portal_name,
-                max_rows: row_limit
-            },
-            Sync]));
+    fn inner_execute(&self, portal_name: &str, row_limit: i32, params: &[&ToSql])
+            -> PostgresResult<()> {
+        try!(self.check_not_aborted());
+        let (formats, values) = try!(self.encode_params(params));
+        let result_formats = self.result_formats();
+
+        let mut messages = vec![];
+        let parse = self.deferred_parse();
+        let deferred = parse.is_some();
+        messages.extend(parse.move_iter());
+        messages.push(Bind {
+            portal: portal_name,
+            statement: self.name.as_slice(),
+            formats: formats.as_slice(),
+            values: values.as_slice(),
+            result_formats: result_formats.as_slice()
+        });
+        messages.push(Execute {
+            portal: portal_name,
+            max_rows: row_limit
+        });
+        messages.push(Sync);
+        try_pg!(self.conn.write_messages(messages.as_slice()));
+
+        if deferred {
+            match try_pg!(self.conn.read_message()) {
+                ParseComplete => self.parsed.set(true),
+                // the statement was not created, so it is parsed again next time
+                ErrorResponse { fields } => {
+                    try!(self.conn.wait_for_ready());
+                    return Err(PgDbError(PostgresDbError::new(fields)));
+                }
+                _ => {
+                    self.conn.conn.borrow_mut().desynchronized = true;
+                    return Err(PgBadResponse);
+                }
+            }
+        }
 
         match try_pg!(self.conn.read_message()) {
             BindComplete => Ok(()),
@@ -1160,113 +3096,818 @@ impl<'conn> PostgresStatement<'conn> {
             data: RingBuf::new(),
             row_limit: row_limit,
             more_rows: true,
+            tag: None,
             finished: false,
         };
         try!(result.read_rows())
 
-        Ok(result)
-    }
+        Ok(result)
+    }
+
+    /// Returns a slice containing the expected parameter types.
+    pub fn param_types<'a>(&'a self) -> &'a [PostgresType] {
+        self.param_types.as_slice()
+    }
+
+    /// Returns a slice describing the columns of the result of the query.
+    pub fn result_descriptions<'a>(&'a self) -> &'a [ResultDescription] {
+        self.result_desc.as_slice()
+    }
+
+    /// Executes the prepared statement, returning the number of rows modified.
+    ///
+    /// If the statement does not modify any rows (e.g. SELECT), 0 is returned.
+    ///
+    /// Returns a `PgWrongParamCount` error without contacting the server if
+    /// the number of parameters provided differs from the number reported by
+    /// `param_types`. This applies to statements which take no parameters as
+    /// well, so a leftover parameter is never silently ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// # let bar = 1i32;
+    /// # let baz = true;
+    /// let stmt = conn.prepare("UPDATE foo SET bar = $1 WHERE baz = $2").unwrap();
+    /// match stmt.execute([&bar, &baz]) {
+    ///     Ok(count) => println!("{} row(s) updated", count),
+    ///     Err(err) => println!("Error executing query: {}", err)
+    /// }
+    pub fn execute(&self, params: &[&ToSql]) -> PostgresResult<uint> {
+        self.execute_returning_tag(params).map(|tag| tag.rows)
+    }
+
+    /// Like `execute`, but returns the full command tag reported by the
+    /// server.
+    ///
+    /// This can be used to distinguish a command which affected no rows from
+    /// one which never reports a row count, such as `CREATE TABLE`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.prepare("INSERT INTO foo (bar) VALUES (1), (2)").unwrap();
+    /// let tag = stmt.execute_returning_tag([]).unwrap();
+    /// assert_eq!("INSERT", tag.kind.as_slice());
+    /// assert_eq!(2, tag.rows);
+    /// ```
+    pub fn execute_returning_tag(&self, params: &[&ToSql])
+            -> PostgresResult<CommandTag> {
+        check_desync!(self.conn);
+        try!(self.inner_execute("", 0, params));
+
+        let tag;
+        loop {
+            match try_pg!(self.conn.read_message()) {
+                DataRow { .. } => {}
+                CopyInResponse { .. } => try_pg!(self.conn.abort_copy_in()),
+                ErrorResponse { fields } => {
+                    try!(self.conn.wait_for_ready());
+                    return Err(PgDbError(PostgresDbError::new(fields)));
+                }
+                CommandComplete { tag: raw } => {
+                    tag = CommandTag::parse(raw.as_slice());
+                    break;
+                }
+                EmptyQueryResponse => {
+                    tag = CommandTag::parse("");
+                    break;
+                }
+                _ => {
+                    self.conn.conn.borrow_mut().desynchronized = true;
+                    return Err(PgBadResponse);
+                }
+            }
+        }
+        try!(self.conn.wait_for_ready());
+
+        Ok(tag)
+    }
+
+    /// Executes the prepared statement once for each set of parameters,
+    /// returning the number of rows modified by each execution.
+    ///
+    /// All executions are sent to the server before any responses are read,
+    /// so the whole batch costs a single round trip rather than one per set of
+    /// parameters. If an execution fails, the server skips the remaining ones
+    /// and the first error is returned.
+    ///
+    /// Outside of a transaction, the whole batch runs in a single implicit
+    /// transaction, so a failure also rolls back the executions which already
+    /// completed. Inside of a transaction, a failure aborts the transaction as
+    /// any other error would.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.prepare("INSERT INTO foo (bar) VALUES ($1)").unwrap();
+    /// # use postgres::types::ToSql;
+    /// let one: &[&ToSql] = &[&1i32];
+    /// let two: &[&ToSql] = &[&2i32];
+    /// let counts = stmt.execute_batch_params([one, two]).unwrap();
+    /// assert_eq!(vec![1, 1], counts);
+    /// ```
+    pub fn execute_batch_params(&self, params: &[&[&ToSql]])
+            -> PostgresResult<Vec<uint>> {
+        check_desync!(self.conn);
+        try!(self.check_not_aborted());
+
+        if params.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut encoded = vec![];
+        for params in params.iter() {
+            encoded.push(try!(self.encode_params(*params)));
+        }
+        let result_formats = self.result_formats();
+
+        let mut messages = vec![];
+        messages.extend(self.deferred_parse().move_iter());
+        for &(ref formats, ref values) in encoded.iter() {
+            messages.push(Bind {
+                portal: "",
+                statement: self.name.as_slice(),
+                formats: formats.as_slice(),
+                values: values.as_slice(),
+                result_formats: result_formats.as_slice()
+            });
+            messages.push(Execute {
+                portal: "",
+                max_rows: 0
+            });
+        }
+        messages.push(Sync);
+        try_pg!(self.conn.write_messages(messages.as_slice()));
+
+        let mut counts = Vec::with_capacity(encoded.len());
+        while counts.len() < encoded.len() {
+            match try_pg!(self.conn.read_message()) {
+                ParseComplete => self.parsed.set(true),
+                BindComplete | DataRow { .. } => {}
+                ErrorResponse { fields } => {
+                    try!(self.conn.wait_for_ready());
+                    return Err(PgDbError(PostgresDbError::new(fields)));
+                }
+                CommandComplete { tag } => {
+                    counts.push(CommandTag::parse(tag.as_slice()).rows);
+                }
+                EmptyQueryResponse => counts.push(0),
+                _ => {
+                    self.conn.conn.borrow_mut().desynchronized = true;
+                    return Err(PgBadResponse);
+                }
+            }
+        }
+        try!(self.conn.wait_for_ready());
+
+        Ok(counts)
+    }
+
+    /// Executes the prepared statement, returning an iterator over the
+    /// resulting rows.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.prepare("SELECT foo FROM bar WHERE baz = $1").unwrap();
+    /// # let baz = true;
+    /// let mut rows = match stmt.query([&baz]) {
+    ///     Ok(rows) => rows,
+    ///     Err(err) => fail!("Error running query: {}", err)
+    /// };
+    /// for row in rows {
+    ///     let foo: i32 = row["foo"];
+    ///     println!("foo: {}", foo);
+    /// }
+    /// ```
+    pub fn query<'a>(&'a self, params: &[&ToSql])
+            -> PostgresResult<PostgresRows<'a>> {
+        check_desync!(self.conn);
+        self.lazy_query(0, params)
+    }
+
+    /// Like `query`, but also returns the number of rows affected by the
+    /// statement.
+    ///
+    /// This is useful for statements with a `RETURNING` clause, such as
+    /// `UPDATE ... RETURNING *`, where both the rows and the count reported in
+    /// the command tag are wanted.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.prepare("UPDATE foo SET bar = 1 RETURNING id").unwrap();
+    /// let (rows, count) = stmt.query_with_count([]).unwrap();
+    /// println!("{} row(s) updated", count);
+    /// for row in rows {
+    ///     let id: i32 = row[0u];
+    ///     println!("updated {}", id);
+    /// }
+    /// ```
+    pub fn query_with_count<'a>(&'a self, params: &[&ToSql])
+            -> PostgresResult<(PostgresRows<'a>, uint)> {
+        let rows = try!(self.query(params));
+        let count = match rows.tag {
+            Some(ref tag) => tag.rows,
+            None => 0,
+        };
+        Ok((rows, count))
+    }
+
+    /// Like `query`, but cancels the query if it has not completed within
+    /// `timeout`.
+    ///
+    /// A background task waits for the timeout to expire and then asks the
+    /// server to cancel the query over a separate connection, as
+    /// `cancel_query` does. Like libpq, the cancellation request is sent
+    /// without SSL. Once the server has reported that the query was canceled,
+    /// `PgQueryTimeout` is returned and the connection is ready to be used
+    /// again. As with any other failed statement, a transaction the query ran
+    /// in is aborted.
+    ///
+    /// If the query completes at about the same time the timeout expires, the
+    /// server ignores the cancellation and the query's result is returned as
+    /// usual.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # use postgres::error::PgQueryTimeout;
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.prepare("SELECT expensive_report()").unwrap();
+    /// match stmt.query_timeout([], Duration::seconds(5)) {
+    ///     Ok(rows) => { /* ... */ }
+    ///     Err(PgQueryTimeout) => println!("the report took too long"),
+    ///     Err(err) => fail!("Error running query: {}", err),
+    /// }
+    /// ```
+    pub fn query_timeout<'a>(&'a self, params: &[&ToSql], timeout: Duration)
+            -> PostgresResult<PostgresRows<'a>> {
+        let (cancel_params, cancel_data) = {
+            let conn = self.conn.conn.borrow();
+            (conn.cancel_params.clone(), conn.cancel_data)
+        };
+
+        let (done_tx, done_rx) = channel::<()>();
+        let (canceled_tx, canceled_rx) = channel();
+        task::spawn(proc() {
+            let mut timer = match timer::Timer::new() {
+                Ok(timer) => timer,
+                Err(_) => {
+                    canceled_tx.send(false);
+                    return;
+                }
+            };
+            let expired = timer.oneshot(cmp::max(timeout.num_milliseconds(), 0) as u64);
+            let mut canceled = false;
+            select! {
+                _ = expired.recv_opt() => {
+                    match cancel_query(cancel_params, &NoSsl, cancel_data) {
+                        Ok(()) => canceled = true,
+                        Err(err) => debug!("Unable to cancel query: {}", err),
+                    }
+                },
+                _ = done_rx.recv_opt() => {}
+            }
+            let _ = canceled_tx.send_opt(canceled);
+        });
+
+        let result = self.query(params);
+        // Wait for the task so it can't cancel a later query
+        let _ = done_tx.send_opt(());
+        let canceled = canceled_rx.recv_opt().unwrap_or(false);
+        match result {
+            Err(PgDbError(PostgresDbError { code: QueryCanceled, .. })) if canceled =>
+                Err(PgQueryTimeout),
+            result => result,
+        }
+    }
+
+    /// Consumes the statement, clearing it from the Postgres session.
+    ///
+    /// Functionally identical to the `Drop` implementation of the
+    /// `PostgresStatement` except that it returns any error to the caller.
+    pub fn finish(mut self) -> PostgresResult<()> {
+        self.finished = true;
+        self.finish_inner()
+    }
+}
+
+/// The command tag reported by the server when a statement completes.
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct CommandTag {
+    /// The kind of command that was executed, e.g. `INSERT` or
+    /// `CREATE TABLE`
+    pub kind: String,
+    /// The OID of the inserted row. Only reported for `INSERT` commands.
+    pub oid: Option<Oid>,
+    /// The number of rows affected by the command, or 0 if the command does
+    /// not report a row count
+    pub rows: uint,
+}
+
+/// A message returned by `simple_query`.
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum SimpleQueryMessage {
+    /// A row returned by a statement, with each value in the text format
+    SimpleQueryRow(Vec<Option<String>>),
+    /// The command tag of a statement which has completed
+    SimpleQueryComplete(CommandTag),
+}
+
+impl CommandTag {
+    fn parse(tag: &str) -> CommandTag {
+        let mut words: Vec<&str> = tag.split(' ').collect();
+
+        let rows = match words.last().and_then(|s| FromStr::from_str(*s)) {
+            Some(rows) => {
+                words.pop();
+                rows
+            }
+            None => 0
+        };
+
+        let oid = match words.last().and_then(|s| FromStr::from_str(*s)) {
+            Some(oid) => {
+                words.pop();
+                Some(oid)
+            }
+            None => None
+        };
+
+        CommandTag {
+            kind: words.connect(" "),
+            oid: oid,
+            rows: rows,
+        }
+    }
+}
+
+/// A statement which has been parsed and described, but whose protocol
+/// exchange with the server has not yet been completed.
+///
+/// Created by `PostgresConnection::describe`. Executing the statement
+/// completes the exchange, as does converting it into a normal statement with
+/// `finish`. If it is dropped instead, the statement is closed and the exchange
+/// completed without executing it.
+pub struct PostgresDescribedStatement<'conn> {
+    stmt: PostgresStatement<'conn>,
+}
+
+impl<'conn> PostgresDescribedStatement<'conn> {
+    /// Returns a slice containing the expected parameter types.
+    ///
+    /// Types unknown to this library are not named until the statement is
+    /// converted with `finish`, since looking the names up requires
+    /// additional queries.
+    pub fn param_types<'a>(&'a self) -> &'a [PostgresType] {
+        self.stmt.param_types()
+    }
+
+    /// Returns a slice describing the columns of the result of the query.
+    ///
+    /// Types unknown to this library are not named until the statement is
+    /// converted with `finish`.
+    pub fn result_descriptions<'a>(&'a self) -> &'a [ResultDescription] {
+        self.stmt.result_descriptions()
+    }
+
+    /// Executes the statement once as part of the same protocol exchange,
+    /// returning the number of rows modified.
+    ///
+    /// The statement is closed afterwards.
+    pub fn execute(mut self, params: &[&ToSql]) -> PostgresResult<uint> {
+        let (formats, values) = try!(self.stmt.encode_params(params));
+        let result_formats = self.stmt.result_formats();
+        try_pg!(self.stmt.conn.write_messages([
+            Bind {
+                portal: "",
+                statement: self.stmt.name.as_slice(),
+                formats: formats.as_slice(),
+                values: values.as_slice(),
+                result_formats: result_formats.as_slice()
+            },
+            Execute {
+                portal: "",
+                max_rows: 0
+            },
+            Close {
+                variant: 'S' as u8,
+                name: self.stmt.name.as_slice()
+            },
+            Sync]));
+
+        let mut num = 0;
+        loop {
+            match try_pg!(self.stmt.conn.read_message()) {
+                BindComplete | DataRow { .. } | EmptyQueryResponse => {}
+                CommandComplete { tag } => {
+                    num = CommandTag::parse(tag.as_slice()).rows;
+                }
+                CloseComplete => break,
+                ErrorResponse { fields } => {
+                    // the server skipped the Close, so leave it to the
+                    // statement's destructor
+                    try!(self.stmt.conn.wait_for_ready());
+                    return Err(PgDbError(PostgresDbError::new(fields)));
+                }
+                _ => {
+                    self.stmt.conn.conn.borrow_mut().desynchronized = true;
+                    return Err(PgBadResponse);
+                }
+            }
+        }
+        self.stmt.finished = true;
+        try!(self.stmt.conn.wait_for_ready());
+
+        Ok(num)
+    }
+
+    /// Completes the protocol exchange, returning a normal prepared statement
+    /// which may be executed any number of times.
+    pub fn finish(self) -> PostgresResult<PostgresStatement<'conn>> {
+        let PostgresDescribedStatement { mut stmt } = self;
+        try_pg!(stmt.conn.write_messages([Sync]));
+        try!(stmt.conn.wait_for_ready());
+
+        {
+            let mut conn = stmt.conn.conn.borrow_mut();
+            try!(conn.set_type_names(stmt.param_types.mut_iter()));
+            try!(conn.set_type_names(stmt.result_desc.make_unique().mut_iter()
+                                         .map(|d| &mut d.ty)));
+            set_result_formats(stmt.result_desc.make_unique().as_mut_slice());
+        }
+
+        Ok(stmt)
+    }
+}
+
+struct PipelineEntry<'a> {
+    stmt: &'a PostgresStatement<'a>,
+    formats: Vec<i16>,
+    values: Vec<Option<Vec<u8>>>,
+}
+
+/// A queue of statement executions which are sent to the server together.
+///
+/// All of the queued executions are sent before a single `Sync` message, so
+/// running a pipeline costs a single round trip regardless of its length.
+///
+/// # Transactions
+///
+/// The server stops processing the pipeline at the first execution which
+/// fails. Outside of a transaction, the whole pipeline runs in a single
+/// implicit transaction, so the failure also rolls back the executions which
+/// already completed. Inside of a transaction, the failure aborts the
+/// transaction as any other error would.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use postgres::{PostgresConnection, NoSsl};
+/// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+/// let insert = conn.prepare("INSERT INTO foo (bar) VALUES ($1)").unwrap();
+/// let update = conn.prepare("UPDATE foo SET bar = bar + 1").unwrap();
+/// let mut pipeline = conn.pipeline();
+/// pipeline.execute(&insert, [&1i32]).unwrap();
+/// pipeline.execute(&update, []).unwrap();
+/// for result in pipeline.run().unwrap().move_iter() {
+///     println!("{}", result);
+/// }
+/// ```
+pub struct PostgresPipeline<'a> {
+    conn: &'a PostgresConnection,
+    entries: Vec<PipelineEntry<'a>>,
+}
+
+impl<'a> PostgresPipeline<'a> {
+    /// Queues an execution of a prepared statement.
+    ///
+    /// The parameters are converted immediately, so an error is returned if
+    /// the statement belongs to a different connection or the parameters are
+    /// invalid.
+    pub fn execute(&mut self, stmt: &'a PostgresStatement<'a>,
+                   params: &[&ToSql]) -> PostgresResult<()> {
+        if self.conn as *const _ != stmt.conn as *const _ {
+            return Err(PgWrongConnection);
+        }
+        let (formats, values) = try!(stmt.encode_params(params));
+        self.entries.push(PipelineEntry {
+            stmt: stmt,
+            formats: formats,
+            values: values,
+        });
+        Ok(())
+    }
+
+    /// Returns the number of queued executions.
+    pub fn len(&self) -> uint {
+        self.entries.len()
+    }
+
+    /// Sends all of the queued executions to the server, returning the number
+    /// of rows modified by each.
+    ///
+    /// The results are in the order the executions were queued. Since the
+    /// server skips every execution after one which fails, the failing
+    /// execution's error is the last result, and the returned vector is
+    /// shorter than the number of queued executions.
+    ///
+    /// An `Err` is returned directly if communication with the server fails.
+    pub fn run(self) -> PostgresResult<Vec<PostgresResult<uint>>> {
+        let results = try!(self.run_inner(false));
+        Ok(results.move_iter().map(|result| result.map(|r| r.rows_affected)).collect())
+    }
+
+    /// Like `run`, but also collects the rows returned by each execution, such
+    /// as those produced by an `UPDATE ... RETURNING` statement.
+    ///
+    /// The server sends the rows of each execution followed by its command
+    /// tag, so the rows received before the `n`th command tag belong to the
+    /// `n`th queued execution. An execution which fails is sent an error in
+    /// place of its command tag, and any rows it had already returned are
+    /// discarded. As with `run`, that error is the last result.
+    pub fn run_with_rows(self)
+            -> PostgresResult<Vec<PostgresResult<PostgresPipelineResult>>> {
+        self.run_inner(true)
+    }
+
+    fn run_inner(self, collect_rows: bool)
+            -> PostgresResult<Vec<PostgresResult<PostgresPipelineResult>>> {
+        check_desync!(self.conn);
+        for entry in self.entries.iter() {
+            try!(entry.stmt.check_not_aborted());
+        }
+
+        let result_formats: Vec<Vec<i16>> = self.entries.iter().map(|entry| {
+            entry.stmt.result_formats()
+        }).collect();
+
+        let mut messages = vec![];
+        // Statements whose Parse is sent, in order. A statement queued more
+        // than once is only parsed before its first execution.
+        let mut parsing: Vec<&PostgresStatement> = vec![];
+        for (entry, result_formats) in self.entries.iter().zip(result_formats.iter()) {
+            if !parsing.iter().any(|stmt| *stmt as *const _ == entry.stmt as *const _) {
+                match entry.stmt.deferred_parse() {
+                    Some(parse) => {
+                        messages.push(parse);
+                        parsing.push(entry.stmt);
+                    }
+                    None => {}
+                }
+            }
+            messages.push(Bind {
+                portal: "",
+                statement: entry.stmt.name.as_slice(),
+                formats: entry.formats.as_slice(),
+                values: entry.values.as_slice(),
+                result_formats: result_formats.as_slice()
+            });
+            messages.push(Execute {
+                portal: "",
+                max_rows: 0
+            });
+        }
+        messages.push(Sync);
+        try_pg!(self.conn.write_messages(messages.as_slice()));
+
+        // After an error the server skips the remaining Parse messages, so
+        // only statements whose ParseComplete arrives are marked as parsed
+        let mut parsing = parsing.move_iter();
+        let mut results = Vec::with_capacity(self.entries.len());
+        let mut rows = vec![];
+        while results.len() < self.entries.len() {
+            match try_pg!(self.conn.read_message()) {
+                ParseComplete => match parsing.next() {
+                    Some(stmt) => stmt.parsed.set(true),
+                    None => {
+                        self.conn.conn.borrow_mut().desynchronized = true;
+                        return Err(PgBadResponse);
+                    }
+                },
+                BindComplete => {}
+                DataRow { row } => {
+                    if collect_rows {
+                        rows.push(PostgresOwnedRow {
+                            desc: self.entries.get(results.len()).stmt.result_desc.clone(),
+                            data: row,
+                        });
+                    }
+                }
+                ErrorResponse { fields } => {
+                    results.push(Err(PgDbError(PostgresDbError::new(fields))));
+                    break;
+                }
+                CommandComplete { tag } => {
+                    results.push(Ok(PostgresPipelineResult {
+                        rows: mem::replace(&mut rows, vec![]),
+                        rows_affected: CommandTag::parse(tag.as_slice()).rows,
+                    }));
+                }
+                EmptyQueryResponse => {
+                    results.push(Ok(PostgresPipelineResult {
+                        rows: vec![],
+                        rows_affected: 0,
+                    }));
+                }
+                _ => {
+                    self.conn.conn.borrow_mut().desynchronized = true;
+                    return Err(PgBadResponse);
+                }
+            }
+        }
+        try!(self.conn.wait_for_ready());
+
+        Ok(results)
+    }
+}
+
+/// The result of a single execution in a `PostgresPipeline`.
+///
+/// Returned by `PostgresPipeline::run_with_rows`.
+pub struct PostgresPipelineResult {
+    /// The rows returned by the execution, in the order they were sent.
+    pub rows: Vec<PostgresOwnedRow>,
+    /// The number of rows modified by the execution, as reported by its
+    /// command tag.
+    pub rows_affected: uint,
+}
+
+/// An in progress `COPY ... FROM STDIN` operation.
+///
+/// Created by `PostgresConnection::copy_in`. Data written to it is sent to the
+/// server immediately. If it is dropped without being finished, the operation
+/// is aborted.
+pub struct PostgresCopyIn<'conn> {
+    conn: &'conn PostgresConnection,
+    finished: bool,
+}
 
-    /// Returns a slice containing the expected parameter types.
-    pub fn param_types<'a>(&'a self) -> &'a [PostgresType] {
-        self.param_types.as_slice()
+#[unsafe_destructor]
+impl<'conn> Drop for PostgresCopyIn<'conn> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.finish_inner(Some("COPY aborted by the client"));
+        }
     }
+}
 
-    /// Returns a slice describing the columns of the result of the query.
-    pub fn result_descriptions<'a>(&'a self) -> &'a [ResultDescription] {
-        self.result_desc.as_slice()
+impl<'conn> Writer for PostgresCopyIn<'conn> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.conn.write_messages([CopyData { data: buf }])
     }
+}
 
-    /// Executes the prepared statement, returning the number of rows modified.
-    ///
-    /// If the statement does not modify any rows (e.g. SELECT), 0 is returned.
-    ///
-    /// # Example
-    ///
-    /// ```rust,no_run
-    /// # use postgres::{PostgresConnection, NoSsl};
-    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
-    /// # let bar = 1i32;
-    /// # let baz = true;
-    /// let stmt = conn.prepare("UPDATE foo SET bar = $1 WHERE baz = $2").unwrap();
-    /// match stmt.execute([&bar, &baz]) {
-    ///     Ok(count) => println!("{} row(s) updated", count),
-    ///     Err(err) => println!("Error executing query: {}", err)
-    /// }
-    pub fn execute(&self, params: &[&ToSql]) -> PostgresResult<uint> {
+impl<'conn> PostgresCopyIn<'conn> {
+    fn finish_inner(&mut self, abort: Option<&str>) -> PostgresResult<uint> {
         check_desync!(self.conn);
-        try!(self.inner_execute("", 0, params));
+        match abort {
+            Some(message) =>
+                try_pg!(self.conn.write_messages([CopyFail { message: message }])),
+            None => try_pg!(self.conn.write_messages([CopyDone])),
+        }
 
-        let num;
+        let mut num = 0;
         loop {
             match try_pg!(self.conn.read_message()) {
-                DataRow { .. } => {}
+                CommandComplete { tag } => {
+                    num = CommandTag::parse(tag.as_slice()).rows;
+                }
                 ErrorResponse { fields } => {
                     try!(self.conn.wait_for_ready());
                     return Err(PgDbError(PostgresDbError::new(fields)));
                 }
-                CommandComplete { tag } => {
-                    let s = tag.as_slice().split(' ').last().unwrap();
-                    num = FromStr::from_str(s).unwrap_or(0);
-                    break;
-                }
-                EmptyQueryResponse => {
-                    num = 0;
-                    break;
-                }
+                ReadyForQuery { .. } => break,
                 _ => {
                     self.conn.conn.borrow_mut().desynchronized = true;
                     return Err(PgBadResponse);
                 }
             }
         }
-        try!(self.conn.wait_for_ready());
 
         Ok(num)
     }
 
-    /// Executes the prepared statement, returning an iterator over the
-    /// resulting rows.
-    ///
-    /// # Example
+    /// Completes the operation, returning the number of rows copied.
     ///
-    /// ```rust,no_run
-    /// # use postgres::{PostgresConnection, NoSsl};
-    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
-    /// let stmt = conn.prepare("SELECT foo FROM bar WHERE baz = $1").unwrap();
-    /// # let baz = true;
-    /// let mut rows = match stmt.query([&baz]) {
-    ///     Ok(rows) => rows,
-    ///     Err(err) => fail!("Error running query: {}", err)
-    /// };
-    /// for row in rows {
-    ///     let foo: i32 = row["foo"];
-    ///     println!("foo: {}", foo);
-    /// }
-    /// ```
-    pub fn query<'a>(&'a self, params: &[&ToSql])
-            -> PostgresResult<PostgresRows<'a>> {
-        check_desync!(self.conn);
-        self.lazy_query(0, params)
+    /// Errors caused by the copied data, such as a malformed value, are
+    /// reported here.
+    pub fn finish(mut self) -> PostgresResult<uint> {
+        self.finished = true;
+        self.finish_inner(None)
     }
 
-    /// Consumes the statement, clearing it from the Postgres session.
-    ///
-    /// Functionally identical to the `Drop` implementation of the
-    /// `PostgresStatement` except that it returns any error to the caller.
-    pub fn finish(mut self) -> PostgresResult<()> {
+    /// Aborts the operation, causing the server to discard the data copied so
+    /// far.
+    pub fn abort(mut self, message: &str) -> PostgresResult<()> {
         self.finished = true;
-        self.finish_inner()
+        match self.finish_inner(Some(message)) {
+            // the server always reports the abort as an error
+            Err(PgDbError(_)) | Ok(_) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+static COPY_SIGNATURE: &'static [u8] = b"PGCOPY\n\xff\r\n\0";
+
+/// Writes typed rows in the binary `COPY` format.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use postgres::{PostgresConnection, BinaryCopyWriter, NoSsl};
+/// # use postgres::types::{PgInt4, PgVarchar};
+/// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+/// let copy = conn.copy_in("COPY foo (id, name) FROM STDIN BINARY").unwrap();
+/// let mut writer = BinaryCopyWriter::new(copy, [PgInt4, PgVarchar]).unwrap();
+/// writer.write_row([&1i32, &"john"]).unwrap();
+/// writer.write_row([&2i32, &None::<String>]).unwrap();
+/// let rows = writer.finish().unwrap().finish().unwrap();
+/// ```
+pub struct BinaryCopyWriter<W> {
+    writer: W,
+    types: Vec<PostgresType>,
+}
+
+impl<W: Writer> BinaryCopyWriter<W> {
+    /// Creates a new writer for rows with columns of the specified types,
+    /// writing the binary `COPY` header to `writer`.
+    pub fn new(mut writer: W, types: &[PostgresType])
+               -> PostgresResult<BinaryCopyWriter<W>> {
+        try_pg!(writer.write(COPY_SIGNATURE));
+        // flags
+        try_pg!(writer.write_be_i32(0));
+        // header extension length
+        try_pg!(writer.write_be_i32(0));
+
+        Ok(BinaryCopyWriter {
+            writer: writer,
+            types: Vec::from_slice(types),
+        })
+    }
+
+    /// Writes a row.
+    ///
+    /// Each value is converted with its `ToSql` implementation for the type
+    /// of its column. Returns `PgWrongColumnCount` if the row does not have
+    /// exactly one value per column, in which case nothing is written.
+    pub fn write_row(&mut self, row: &[&ToSql]) -> PostgresResult<()> {
+        if row.len() != self.types.len() {
+            return Err(PgWrongColumnCount {
+                expected: self.types.len(),
+                actual: row.len(),
+            });
+        }
+
+        let mut buf = MemWriter::new();
+        try_pg!(buf.write_be_i16(row.len() as i16));
+        for (value, ty) in row.iter().zip(self.types.iter()) {
+            match try!(value.to_sql(ty)) {
+                (_, Some(value)) => {
+                    try_pg!(buf.write_be_i32(value.len() as i32));
+                    try_pg!(buf.write(value.as_slice()));
+                }
+                (_, None) => try_pg!(buf.write_be_i32(-1)),
+            }
+        }
+
+        Ok(try_pg!(self.writer.write(buf.unwrap().as_slice())))
+    }
+
+    /// Writes the binary `COPY` trailer, returning the underlying writer.
+    pub fn finish(mut self) -> PostgresResult<W> {
+        try_pg!(self.writer.write_be_i16(-1));
+        Ok(self.writer)
     }
 }
 
 /// Information about a column of the result of a query.
-#[deriving(PartialEq, Eq)]
+#[deriving(PartialEq, Eq, Clone)]
 pub struct ResultDescription {
     /// The name of the column
     pub name: String,
     /// The type of the data in the column
-    pub ty: PostgresType
+    pub ty: PostgresType,
+    /// The format the column's values are returned in.
+    ///
+    /// Values in the text format are decoded with `FromSql::from_sql_text`
+    /// rather than `FromSql::from_sql`.
+    pub format: Format,
 }
 
 /// An iterator over the resulting rows of a query.
@@ -1276,6 +3917,7 @@ pub struct PostgresRows<'stmt> {
     data: RingBuf<Vec<Option<Vec<u8>>>>,
     row_limit: i32,
     more_rows: bool,
+    tag: Option<CommandTag>,
     finished: bool,
 }
 
@@ -1313,9 +3955,14 @@ impl<'stmt> PostgresRows<'stmt> {
     fn read_rows(&mut self) -> PostgresResult<()> {
         loop {
             match try_pg!(self.stmt.conn.read_message()) {
-                EmptyQueryResponse |
-                CommandComplete { .. } => {
+                EmptyQueryResponse => {
                     self.more_rows = false;
+                    self.tag = Some(CommandTag::parse(""));
+                    break;
+                },
+                CommandComplete { tag } => {
+                    self.more_rows = false;
+                    self.tag = Some(CommandTag::parse(tag.as_slice()));
                     break;
                 },
                 PortalSuspended => {
@@ -1323,6 +3970,14 @@ impl<'stmt> PostgresRows<'stmt> {
                     break;
                 },
                 DataRow { row } => self.data.push_back(row),
+                CopyInResponse { .. } => try_pg!(self.stmt.conn.abort_copy_in()),
+                ErrorResponse { fields } => {
+                    // The rows sent before the error are an incomplete result
+                    self.more_rows = false;
+                    self.data.clear();
+                    try!(self.stmt.conn.wait_for_ready());
+                    return Err(PgDbError(PostgresDbError::new(fields)));
+                }
                 _ => {
                     self.stmt.conn.conn.borrow_mut().desynchronized = true;
                     return Err(PgBadResponse);
@@ -1402,12 +4057,301 @@ impl<'stmt> PostgresRow<'stmt> {
     /// Returns an `Error` value if the index does not reference a column or
     /// the return type is not compatible with the Postgres type.
     pub fn get<I: RowIndex, T: FromSql>(&self, idx: I) -> PostgresResult<T> {
+        get_column(self.stmt.result_descriptions(), self.data.as_slice(), idx)
+    }
+
+    /// Retrieves the contents of an integer field of the row, widened to an
+    /// `i64`.
+    ///
+    /// Unlike `get`, this accepts a column of any of the `SMALLINT`, `INT` and
+    /// `BIGINT` types. `None` is returned for a NULL value.
+    ///
+    /// Returns an `Error` value if the index does not reference a column or
+    /// the column is not of an integer type.
+    pub fn get_as_i64<I: RowIndex>(&self, idx: I)
+                                   -> PostgresResult<Option<i64>> {
         let idx = match idx.idx(self.stmt) {
             Some(idx) => idx,
             None => return Err(PgInvalidColumn)
         };
-        FromSql::from_sql(&self.stmt.result_desc.get(idx).ty,
-                          self.data.get(idx))
+        let ty = &self.stmt.result_desc.get(idx).ty;
+        let raw = self.data.get(idx);
+        match *ty {
+            PgInt2 => {
+                let value: Option<i16> = try!(FromSql::from_sql(ty, raw));
+                Ok(value.map(|v| v as i64))
+            }
+            PgInt4 => {
+                let value: Option<i32> = try!(FromSql::from_sql(ty, raw));
+                Ok(value.map(|v| v as i64))
+            }
+            PgInt8 => FromSql::from_sql(ty, raw),
+            _ => Err(PgWrongType(ty.clone()))
+        }
+    }
+
+    /// Retrieves the raw binary contents of a field of the row, without
+    /// converting it to a Rust type.
+    ///
+    /// `None` is returned for a NULL value. This avoids an extra copy of large
+    /// `BYTEA` or `TEXT` values; the slice can be wrapped in a `BufReader` to
+    /// process it as a stream.
+    ///
+    /// Returns an `Error` value if the index does not reference a column.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// use std::io::BufReader;
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.prepare("SELECT data FROM blobs").unwrap();
+    /// for row in stmt.query([]).unwrap() {
+    ///     match row.get_bytes(0u).unwrap() {
+    ///         Some(bytes) => {
+    ///             let mut reader = BufReader::new(bytes);
+    ///             // ...
+    ///         }
+    ///         None => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn get_bytes<'a, I: RowIndex>(&'a self, idx: I)
+                                      -> PostgresResult<Option<&'a [u8]>> {
+        get_column_bytes(self.stmt.result_descriptions(), self.data.as_slice(),
+                         idx)
+    }
+
+    /// Determines if a field of the row is NULL, without converting it to a
+    /// Rust type.
+    ///
+    /// # Failure
+    ///
+    /// Fails if the index does not reference a column.
+    pub fn is_null<I: RowIndex+Clone+fmt::Show>(&self, idx: I) -> bool {
+        column_is_null(self.stmt.result_descriptions(), self.data.as_slice(), idx)
+    }
+
+    /// Retrieves the contents of a field of the row as a string in the
+    /// Postgres text format, whatever the type of the column.
+    ///
+    /// `None` is returned for a NULL value. This can be used to read columns
+    /// of types which have no `FromSql` implementation, such as those defined
+    /// by extensions, since the values of types not known to this library are
+    /// always sent in the text format.
+    ///
+    /// Returns an `Error` value if the index does not reference a column, if
+    /// the value was sent in the binary format, or if it is not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.prepare("SELECT '$.a'::jsonpath").unwrap();
+    /// for row in stmt.query([]).unwrap() {
+    ///     let path: Option<String> = row.get_raw_text(0u).unwrap();
+    /// }
+    /// ```
+    pub fn get_raw_text<I: RowIndex>(&self, idx: I) -> PostgresResult<Option<String>> {
+        get_column_text(self.stmt.result_descriptions(), self.data.as_slice(), idx)
+    }
+
+    /// Converts the row into a map from column names to values in the text
+    /// format, without knowing the types of its columns ahead of time.
+    ///
+    /// NULL values are mapped to `None`. Values sent in the text format are
+    /// used as they are. Values sent in the binary format are formatted like
+    /// Postgres prints them for the boolean, integer, string, `NUMERIC`,
+    /// `BYTEA`, `UUID`, `DATE` and `TIME` types, and with their `Show`
+    /// implementation for the floating point types. If several columns have
+    /// the same name, the value of the last one is kept.
+    ///
+    /// Returns an `Error` value if a column of any other type is sent in the
+    /// binary format, or if a text value is not valid UTF-8.
+    pub fn to_string_map(&self) -> PostgresResult<HashMap<String, Option<String>>> {
+        row_to_string_map(self.stmt.result_descriptions(), self.data.as_slice())
+    }
+
+    /// Converts the entire row into a value, typically a tuple with one
+    /// element per column.
+    ///
+    /// Returns an `Error` value if the number of columns does not match or if
+    /// any column cannot be converted.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, NoSsl};
+    /// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+    /// let stmt = conn.prepare("SELECT id, name, score FROM foo").unwrap();
+    /// for row in stmt.query([]).unwrap() {
+    ///     let (id, name, score): (i32, String, Option<f64>) =
+    ///         row.into_tuple().unwrap();
+    /// }
+    /// ```
+    pub fn into_tuple<T: FromRow>(self) -> PostgresResult<T> {
+        FromRow::from_row(&self)
+    }
+
+    /// Converts the row into one which does not borrow the statement that
+    /// produced it.
+    ///
+    /// The column descriptions are shared with the statement rather than
+    /// copied.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{PostgresConnection, PostgresOwnedRow, NoSsl};
+    /// fn load(conn: &PostgresConnection) -> Vec<PostgresOwnedRow> {
+    ///     let stmt = conn.prepare("SELECT id, name FROM foo").unwrap();
+    ///     let rows = stmt.query([]).unwrap();
+    ///     let rows = rows.map(|row| row.into_owned()).collect();
+    ///     rows
+    /// }
+    /// ```
+    pub fn into_owned(self) -> PostgresOwnedRow {
+        PostgresOwnedRow {
+            desc: self.stmt.result_desc.clone(),
+            data: self.data,
+        }
+    }
+}
+
+fn is_retryable(code: &PostgresSqlState) -> bool {
+    match *code {
+        SerializationFailure | DeadlockDetected => true,
+        _ => false,
+    }
+}
+
+// The format of a column of an unknown type depends on the type's name, so it
+// has to be set again once the name has been loaded.
+fn set_result_formats(descs: &mut [ResultDescription]) {
+    for desc in descs.mut_iter() {
+        desc.format = desc.ty.result_format();
+    }
+}
+
+fn get_column<I: RowIndex, T: FromSql>(desc: &[ResultDescription],
+                                       data: &[Option<Vec<u8>>], idx: I)
+                                       -> PostgresResult<T> {
+    let idx = match idx.idx_in(desc) {
+        Some(idx) => idx,
+        None => return Err(PgInvalidColumn)
+    };
+    let result = match desc[idx].format {
+        Binary => FromSql::from_sql(&desc[idx].ty, &data[idx]),
+        Text => {
+            let text = match data[idx] {
+                Some(ref buf) => match String::from_utf8(buf.clone()) {
+                    Ok(s) => Some(s),
+                    Err(_) => return Err(PgBadData),
+                },
+                None => None,
+            };
+            FromSql::from_sql_text(&desc[idx].ty, &text)
+        }
+    };
+    match result {
+        Err(PgBadFieldLength { column: None, ty }) => Err(PgBadFieldLength {
+            column: Some(desc[idx].name.clone()),
+            ty: ty,
+        }),
+        result => result,
+    }
+}
+
+fn get_column_bytes<'a, I: RowIndex>(desc: &[ResultDescription],
+                                     data: &'a [Option<Vec<u8>>], idx: I)
+                                     -> PostgresResult<Option<&'a [u8]>> {
+    let idx = match idx.idx_in(desc) {
+        Some(idx) => idx,
+        None => return Err(PgInvalidColumn)
+    };
+    Ok(data[idx].as_ref().map(|data| data.as_slice()))
+}
+
+fn get_column_text<I: RowIndex>(desc: &[ResultDescription],
+                                data: &[Option<Vec<u8>>], idx: I)
+                                -> PostgresResult<Option<String>> {
+    let idx = match idx.idx_in(desc) {
+        Some(idx) => idx,
+        None => return Err(PgInvalidColumn)
+    };
+    match desc[idx].format {
+        Text => {}
+        Binary => return Err(PgWrongType(desc[idx].ty.clone())),
+    }
+    match data[idx] {
+        Some(ref buf) => match String::from_utf8(buf.clone()) {
+            Ok(s) => Ok(Some(s)),
+            Err(_) => Err(PgBadData),
+        },
+        None => Ok(None),
+    }
+}
+
+fn row_to_string_map(desc: &[ResultDescription], data: &[Option<Vec<u8>>])
+                     -> PostgresResult<HashMap<String, Option<String>>> {
+    let mut map = HashMap::new();
+    for (desc, value) in desc.iter().zip(data.iter()) {
+        let text = match (desc.format, value) {
+            (_, &None) => None,
+            (Text, &Some(ref buf)) => match String::from_utf8(buf.clone()) {
+                Ok(s) => Some(s),
+                Err(_) => return Err(PgBadData),
+            },
+            (Binary, &Some(_)) => Some(try!(binary_value_to_text(&desc.ty, value))),
+        };
+        map.insert(desc.name.clone(), text);
+    }
+    Ok(map)
+}
+
+fn binary_value_to_text(ty: &PostgresType, raw: &Option<Vec<u8>>) -> PostgresResult<String> {
+    fn show<T: FromSql+fmt::Show>(ty: &PostgresType, raw: &Option<Vec<u8>>)
+                                  -> PostgresResult<String> {
+        let value: T = try!(FromSql::from_sql(ty, raw));
+        Ok(value.to_str())
+    }
+
+    match *ty {
+        PgBool => {
+            let value: bool = try!(FromSql::from_sql(ty, raw));
+            Ok(if value { "t" } else { "f" }.to_str())
+        }
+        PgInt2 => show::<i16>(ty, raw),
+        PgInt4 => show::<i32>(ty, raw),
+        PgInt8 => show::<i64>(ty, raw),
+        PgOid => show::<u32>(ty, raw),
+        PgFloat4 => show::<f32>(ty, raw),
+        PgFloat8 => show::<f64>(ty, raw),
+        PgText | PgVarchar | PgCharN | PgName | PgXml | PgJson | PgJsonb =>
+            show::<String>(ty, raw),
+        PgNumeric => show::<Numeric>(ty, raw),
+        PgByteA => {
+            let value: Vec<u8> = try!(FromSql::from_sql(ty, raw));
+            Ok(format!("\\x{}", value.as_slice().to_hex()))
+        }
+        PgUuid => {
+            let value: Uuid = try!(FromSql::from_sql(ty, raw));
+            Ok(value.to_hyphenated_str())
+        }
+        PgDate => show::<Date>(ty, raw),
+        PgTime => show::<Time>(ty, raw),
+        PgTimeTz => show::<TimeTz>(ty, raw),
+        _ => Err(PgWrongType(ty.clone())),
+    }
+}
+
+fn column_is_null<I: RowIndex+Clone+fmt::Show>(desc: &[ResultDescription],
+                                               data: &[Option<Vec<u8>>], idx: I)
+                                               -> bool {
+    match get_column_bytes(desc, data, idx.clone()) {
+        Ok(value) => value.is_none(),
+        Err(err) => fail!("error retrieving column {}: {}", idx, err)
     }
 }
 
@@ -1449,17 +4393,114 @@ impl<'stmt, I: RowIndex+Clone+fmt::Show, T: FromSql> Index<I, T>
     }
 }
 
+/// A result row of a query which owns its data.
+///
+/// Created by `PostgresRow::into_owned`. Unlike a `PostgresRow`, it does not
+/// borrow the statement that produced it, so it may be stored or returned from
+/// a function after the statement has been dropped.
+pub struct PostgresOwnedRow {
+    desc: Arc<Vec<ResultDescription>>,
+    data: Vec<Option<Vec<u8>>>
+}
+
+impl PostgresOwnedRow {
+    /// Like `PostgresRow::get`.
+    pub fn get<I: RowIndex, T: FromSql>(&self, idx: I) -> PostgresResult<T> {
+        get_column(self.desc.as_slice(), self.data.as_slice(), idx)
+    }
+
+    /// Like `PostgresRow::get_bytes`.
+    pub fn get_bytes<'a, I: RowIndex>(&'a self, idx: I)
+                                      -> PostgresResult<Option<&'a [u8]>> {
+        get_column_bytes(self.desc.as_slice(), self.data.as_slice(), idx)
+    }
+
+    /// Like `PostgresRow::get_raw_text`.
+    pub fn get_raw_text<I: RowIndex>(&self, idx: I) -> PostgresResult<Option<String>> {
+        get_column_text(self.desc.as_slice(), self.data.as_slice(), idx)
+    }
+
+    /// Like `PostgresRow::to_string_map`.
+    pub fn to_string_map(&self) -> PostgresResult<HashMap<String, Option<String>>> {
+        row_to_string_map(self.desc.as_slice(), self.data.as_slice())
+    }
+
+    /// Like `PostgresRow::is_null`.
+    pub fn is_null<I: RowIndex+Clone+fmt::Show>(&self, idx: I) -> bool {
+        column_is_null(self.desc.as_slice(), self.data.as_slice(), idx)
+    }
+
+    /// Returns a slice describing the columns of the row.
+    pub fn result_descriptions<'a>(&'a self) -> &'a [ResultDescription] {
+        self.desc.as_slice()
+    }
+}
+
+impl Collection for PostgresOwnedRow {
+    #[inline]
+    fn len(&self) -> uint {
+        self.data.len()
+    }
+}
+
+impl<I: RowIndex+Clone+fmt::Show, T: FromSql> Index<I, T> for PostgresOwnedRow {
+    /// Like the `Index` implementation of `PostgresRow`.
+    fn index(&self, idx: &I) -> T {
+        match self.get(idx.clone()) {
+            Ok(ok) => ok,
+            Err(err) => fail!("error retrieving column {}: {}", idx, err)
+        }
+    }
+}
+
+/// A trait implemented by types that can be created from an entire row.
+pub trait FromRow {
+    /// Creates a new value of this type from a row.
+    fn from_row(row: &PostgresRow) -> PostgresResult<Self>;
+}
+
+macro_rules! from_row_tuple_impl(
+    ($len:expr, $($t:ident $idx:expr),+) => (
+        impl<$($t: FromSql),+> FromRow for ($($t,)+) {
+            fn from_row(row: &PostgresRow) -> PostgresResult<($($t,)+)> {
+                if row.len() != $len {
+                    return Err(PgWrongColumnCount {
+                        expected: $len,
+                        actual: row.len(),
+                    });
+                }
+                Ok(($(try!(row.get($idx)),)+))
+            }
+        }
+    )
+)
+
+from_row_tuple_impl!(1, A 0u)
+from_row_tuple_impl!(2, A 0u, B 1u)
+from_row_tuple_impl!(3, A 0u, B 1u, C 2u)
+from_row_tuple_impl!(4, A 0u, B 1u, C 2u, D 3u)
+from_row_tuple_impl!(5, A 0u, B 1u, C 2u, D 3u, E 4u)
+from_row_tuple_impl!(6, A 0u, B 1u, C 2u, D 3u, E 4u, F 5u)
+from_row_tuple_impl!(7, A 0u, B 1u, C 2u, D 3u, E 4u, F 5u, G 6u)
+from_row_tuple_impl!(8, A 0u, B 1u, C 2u, D 3u, E 4u, F 5u, G 6u, H 7u)
+
 /// A trait implemented by types that can index into columns of a row.
 pub trait RowIndex {
     /// Returns the index of the appropriate column, or `None` if no such
     /// column exists.
-    fn idx(&self, stmt: &PostgresStatement) -> Option<uint>;
+    fn idx(&self, stmt: &PostgresStatement) -> Option<uint> {
+        self.idx_in(stmt.result_descriptions())
+    }
+
+    /// Like `idx`, but looks the column up in a list of column descriptions
+    /// rather than in a statement.
+    fn idx_in(&self, desc: &[ResultDescription]) -> Option<uint>;
 }
 
 impl RowIndex for uint {
     #[inline]
-    fn idx(&self, stmt: &PostgresStatement) -> Option<uint> {
-        if *self > stmt.result_desc.len() {
+    fn idx_in(&self, desc: &[ResultDescription]) -> Option<uint> {
+        if *self >= desc.len() {
             None
         } else {
             Some(*self)
@@ -1469,9 +4510,8 @@ impl RowIndex for uint {
 
 impl<'a> RowIndex for &'a str {
     #[inline]
-    fn idx(&self, stmt: &PostgresStatement) -> Option<uint> {
-        stmt.result_descriptions().iter()
-            .position(|d| d.name.as_slice() == *self)
+    fn idx_in(&self, desc: &[ResultDescription]) -> Option<uint> {
+        desc.iter().position(|d| d.name.as_slice() == *self)
     }
 }
 
@@ -1,4 +1,5 @@
-use std::io::{IoResult, MemWriter, MemReader};
+use std::io::{IoError, IoResult, MemWriter, MemReader, InvalidInput};
+use std::uint;
 use std::mem;
 
 use types::Oid;
@@ -10,13 +11,26 @@ pub static SSL_CODE: i32 = 80877103;
 pub enum BackendMessage {
     AuthenticationCleartextPassword,
     AuthenticationGSS,
+    AuthenticationGSSContinue,
     AuthenticationKerberosV5,
     AuthenticationMD5Password {
         pub salt: [u8, ..4]
     },
     AuthenticationOk,
+    AuthenticationSASL {
+        pub mechanisms: Vec<String>
+    },
+    AuthenticationSASLContinue {
+        pub data: Vec<u8>
+    },
+    AuthenticationSASLFinal {
+        pub data: Vec<u8>
+    },
     AuthenticationSCMCredential,
     AuthenticationSSPI,
+    AuthenticationUnknown {
+        pub code: i32
+    },
     BackendKeyData {
         pub process_id: i32,
         pub secret_key: i32
@@ -26,6 +40,10 @@ pub enum BackendMessage {
     CommandComplete {
         pub tag: String,
     },
+    CopyInResponse {
+        pub format: u8,
+        pub column_formats: Vec<i16>,
+    },
     DataRow {
         pub row: Vec<Option<Vec<u8>>>
     },
@@ -52,7 +70,7 @@ pub enum BackendMessage {
     ParseComplete,
     PortalSuspended,
     ReadyForQuery {
-        pub _state: u8
+        pub state: u8
     },
     RowDescription {
         pub descriptions: Vec<RowDescriptionEntry>
@@ -86,6 +104,13 @@ pub enum FrontendMessage<'a> {
         pub variant: u8,
         pub name: &'a str
     },
+    CopyData {
+        pub data: &'a [u8],
+    },
+    CopyDone,
+    CopyFail {
+        pub message: &'a str,
+    },
     Describe {
         pub variant: u8,
         pub name: &'a str
@@ -94,6 +119,7 @@ pub enum FrontendMessage<'a> {
         pub portal: &'a str,
         pub max_rows: i32
     },
+    Flush,
     Parse {
         pub name: &'a str,
         pub query: &'a str,
@@ -105,6 +131,13 @@ pub enum FrontendMessage<'a> {
     Query {
         pub query: &'a str
     },
+    SASLInitialResponse {
+        pub mechanism: &'a str,
+        pub data: &'a [u8]
+    },
+    SASLResponse {
+        pub data: &'a [u8]
+    },
     SslRequest {
         pub code: i32
     },
@@ -177,6 +210,17 @@ impl<W: Writer> WriteMessage for W {
                 try!(buf.write_u8(variant));
                 try!(buf.write_cstr(name));
             }
+            CopyData { data } => {
+                ident = Some('d');
+                try!(buf.write(data));
+            }
+            CopyDone => {
+                ident = Some('c');
+            }
+            CopyFail { message } => {
+                ident = Some('f');
+                try!(buf.write_cstr(message));
+            }
             Describe { variant, name } => {
                 ident = Some('D');
                 try!(buf.write_u8(variant));
@@ -187,6 +231,9 @@ impl<W: Writer> WriteMessage for W {
                 try!(buf.write_cstr(portal));
                 try!(buf.write_be_i32(max_rows));
             }
+            Flush => {
+                ident = Some('H');
+            }
             Parse { name, query, param_types } => {
                 ident = Some('P');
                 try!(buf.write_cstr(name));
@@ -204,6 +251,16 @@ impl<W: Writer> WriteMessage for W {
                 ident = Some('Q');
                 try!(buf.write_cstr(query));
             }
+            SASLInitialResponse { mechanism, data } => {
+                ident = Some('p');
+                try!(buf.write_cstr(mechanism));
+                try!(buf.write_be_i32(data.len() as i32));
+                try!(buf.write(data));
+            }
+            SASLResponse { data } => {
+                ident = Some('p');
+                try!(buf.write(data));
+            }
             StartupMessage { version, parameters } => {
                 try!(buf.write_be_i32(version));
                 for &(ref k, ref v) in parameters.iter() {
@@ -244,20 +301,46 @@ impl<R: Buffer> ReadCStr for R {
     fn read_cstr(&mut self) -> IoResult<String> {
         let mut buf = try!(self.read_until(0));
         buf.pop();
-        Ok(String::from_utf8(buf).unwrap())
+        String::from_utf8(buf).map_err(|_| IoError {
+            kind: InvalidInput,
+            desc: "string is not valid UTF-8",
+            detail: None,
+        })
     }
 }
 
 #[doc(hidden)]
 pub trait ReadMessage {
-    fn read_message(&mut self) -> IoResult<BackendMessage>;
+    fn read_message(&mut self) -> IoResult<BackendMessage> {
+        self.read_message_limited(uint::MAX)
+    }
+
+    // Fails without reading the body of a message whose length exceeds
+    // max_len, instead of trusting the length enough to allocate for it
+    fn read_message_limited(&mut self, max_len: uint) -> IoResult<BackendMessage>;
 }
 
 impl<R: Reader> ReadMessage for R {
-    fn read_message(&mut self) -> IoResult<BackendMessage> {
+    fn read_message_limited(&mut self, max_len: uint) -> IoResult<BackendMessage> {
         let ident = try!(self.read_u8());
+        let len = try!(self.read_be_i32());
+        if len < mem::size_of::<i32>() as i32 {
+            return Err(IoError {
+                kind: InvalidInput,
+                desc: "invalid message length",
+                detail: Some(format!("{}", len)),
+            });
+        }
         // subtract size of length value
-        let len = try!(self.read_be_i32()) as uint - mem::size_of::<i32>();
+        let len = len as uint - mem::size_of::<i32>();
+        if len > max_len {
+            return Err(IoError {
+                kind: InvalidInput,
+                desc: "message length exceeds the maximum",
+                detail: Some(format!("{} bytes, but at most {} are allowed",
+                                     len, max_len)),
+            });
+        }
         let mut buf = MemReader::new(try!(self.read_exact(len)));
 
         let ret = match ident as char {
@@ -272,6 +355,7 @@ impl<R: Reader> ReadMessage for R {
             'C' => CommandComplete { tag: try!(buf.read_cstr()) },
             'D' => try!(read_data_row(&mut buf)),
             'E' => ErrorResponse { fields: try!(read_fields(&mut buf)) },
+            'G' => try!(read_copy_in_response(&mut buf)),
             'I' => EmptyQueryResponse,
             'K' => BackendKeyData {
                 process_id: try!(buf.read_be_i32()),
@@ -287,13 +371,32 @@ impl<R: Reader> ReadMessage for R {
             },
             't' => try!(read_parameter_description(&mut buf)),
             'T' => try!(read_row_description(&mut buf)),
-            'Z' => ReadyForQuery { _state: try!(buf.read_u8()) },
+            'Z' => ReadyForQuery { state: try!(buf.read_u8()) },
             ident => fail!("Unknown message identifier `{}`", ident)
         };
         Ok(ret)
     }
 }
 
+fn read_copy_in_response(buf: &mut MemReader) -> IoResult<BackendMessage> {
+    let format = try!(buf.read_u8());
+    let len = try!(buf.read_be_i16());
+    if len < 0 {
+        return Err(IoError {
+            kind: InvalidInput,
+            desc: "invalid column count",
+            detail: Some(format!("{}", len)),
+        });
+    }
+    let mut column_formats = vec![];
+
+    for _ in range(0, len) {
+        column_formats.push(try!(buf.read_be_i16()));
+    }
+
+    Ok(CopyInResponse { format: format, column_formats: column_formats })
+}
+
 fn read_fields(buf: &mut MemReader) -> IoResult<Vec<(u8, String)>> {
     let mut fields = vec![];
     loop {
@@ -335,8 +438,22 @@ fn read_auth_message(buf: &mut MemReader) -> IoResult<BackendMessage> {
         },
         6 => AuthenticationSCMCredential,
         7 => AuthenticationGSS,
+        8 => AuthenticationGSSContinue,
         9 => AuthenticationSSPI,
-        val => fail!("Invalid authentication identifier `{}`", val)
+        10 => {
+            let mut mechanisms = vec![];
+            loop {
+                let mechanism = try!(buf.read_cstr());
+                if mechanism.is_empty() {
+                    break;
+                }
+                mechanisms.push(mechanism);
+            }
+            AuthenticationSASL { mechanisms: mechanisms }
+        }
+        11 => AuthenticationSASLContinue { data: try!(buf.read_to_end()) },
+        12 => AuthenticationSASLFinal { data: try!(buf.read_to_end()) },
+        code => AuthenticationUnknown { code: code }
     })
 }
 
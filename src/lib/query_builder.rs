@@ -0,0 +1,97 @@
+//! Incremental construction of queries with bound parameters
+//!
+//! A `QueryBuilder` assembles the text of a query from SQL fragments and
+//! parameters. Each bound parameter is given the next `$N` placeholder, so
+//! values never need to be spliced into the query text.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use postgres::{PostgresConnection, NoSsl};
+//! # use postgres::types::ToSql;
+//! use postgres::query_builder::QueryBuilder;
+//!
+//! # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+//! # let name: Option<&str> = Some("bob");
+//! let ids = [&1i32 as &ToSql, &2i32, &3i32];
+//! let mut builder = QueryBuilder::new("SELECT * FROM people WHERE id IN (");
+//! builder.push_bind_list(ids).push(")");
+//! match name {
+//!     Some(ref name) => { builder.push(" AND name = ").push_bind(name); }
+//!     None => {}
+//! }
+//!
+//! let stmt = conn.prepare(builder.sql()).unwrap();
+//! for row in stmt.query(builder.params()).unwrap() {
+//!     // ...
+//! }
+//! ```
+
+use types::ToSql;
+
+/// A builder for a query and its parameters.
+pub struct QueryBuilder<'a> {
+    sql: String,
+    params: Vec<&'a ToSql>,
+}
+
+impl<'a> QueryBuilder<'a> {
+    /// Creates a new `QueryBuilder` with the query text starting with `sql`.
+    pub fn new(sql: &str) -> QueryBuilder<'a> {
+        QueryBuilder {
+            sql: sql.to_str(),
+            params: vec![],
+        }
+    }
+
+    /// Appends a fragment of SQL to the query.
+    ///
+    /// The fragment is added to the query text as is, so it must never
+    /// contain untrusted input. Use `push_bind` for values.
+    pub fn push<'b>(&'b mut self, sql: &str) -> &'b mut QueryBuilder<'a> {
+        self.sql.push_str(sql);
+        self
+    }
+
+    /// Appends a placeholder for `value` to the query, binding `value` as the
+    /// next parameter.
+    pub fn push_bind<'b>(&'b mut self, value: &'a ToSql)
+                         -> &'b mut QueryBuilder<'a> {
+        self.params.push(value);
+        let placeholder = format!("${}", self.params.len());
+        self.sql.push_str(placeholder.as_slice());
+        self
+    }
+
+    /// Appends a comma separated list of placeholders for `values` to the
+    /// query, binding each value in order.
+    ///
+    /// This is useful for building `IN` lists and `VALUES` rows. Nothing is
+    /// appended if `values` is empty.
+    pub fn push_bind_list<'b>(&'b mut self, values: &[&'a ToSql])
+                              -> &'b mut QueryBuilder<'a> {
+        for (i, &value) in values.iter().enumerate() {
+            if i != 0 {
+                self.sql.push_str(", ");
+            }
+            self.push_bind(value);
+        }
+        self
+    }
+
+    /// Returns the query text built so far.
+    pub fn sql<'b>(&'b self) -> &'b str {
+        self.sql.as_slice()
+    }
+
+    /// Returns the parameters bound so far, in placeholder order.
+    pub fn params<'b>(&'b self) -> &'b [&'a ToSql] {
+        self.params.as_slice()
+    }
+
+    /// Consumes the `QueryBuilder`, returning the query text and its
+    /// parameters.
+    pub fn build(self) -> (String, Vec<&'a ToSql>) {
+        (self.sql, self.params)
+    }
+}
@@ -0,0 +1,173 @@
+//! SCRAM-SHA-256 authentication, as described in RFC 5802 and RFC 7677
+
+use openssl::crypto::hash::{SHA256, Hasher};
+use openssl::crypto::hmac::HMAC;
+use openssl::crypto::rand::rand_bytes;
+use serialize::base64::{ToBase64, FromBase64, STANDARD};
+use std::from_str::FromStr;
+use std::str;
+
+use error::{PostgresConnectError, PgConnectBadResponse};
+
+pub static SCRAM_SHA_256: &'static str = "SCRAM-SHA-256";
+
+static NONCE_LENGTH: uint = 18;
+
+// We never use channel binding, so the GS2 header is always "n,,". The
+// SCRAM-SHA-256-PLUS mechanism is therefore never selected, even over SSL.
+static GS2_HEADER: &'static str = "n,,";
+
+enum State {
+    // client nonce, client-first-message-bare
+    Update(String, String),
+    // server signature
+    Finish(Vec<u8>),
+    Done,
+}
+
+/// The client side of a SCRAM-SHA-256 exchange.
+pub struct ScramSha256 {
+    password: Vec<u8>,
+    message: String,
+    state: State,
+}
+
+impl ScramSha256 {
+    /// Begins a new exchange.
+    ///
+    /// The password is used as-is; SASLprep normalization is not performed.
+    pub fn new(password: &str) -> ScramSha256 {
+        let nonce = rand_bytes(NONCE_LENGTH).as_slice().to_base64(STANDARD);
+        // Postgres ignores the SCRAM username in favor of the startup user
+        let client_first_bare = format!("n=,r={}", nonce);
+        let message = format!("{}{}", GS2_HEADER, client_first_bare);
+
+        ScramSha256 {
+            password: Vec::from_slice(password.as_bytes()),
+            message: message,
+            state: Update(nonce, client_first_bare),
+        }
+    }
+
+    /// Returns the message which should be sent to the server next.
+    pub fn message<'a>(&'a self) -> &'a [u8] {
+        self.message.as_bytes()
+    }
+
+    /// Processes the server-first-message.
+    pub fn update(&mut self, data: &[u8])
+                  -> Result<(), PostgresConnectError> {
+        let (nonce, client_first_bare) = match self.state {
+            Update(ref nonce, ref bare) => (nonce.clone(), bare.clone()),
+            _ => return Err(PgConnectBadResponse),
+        };
+
+        let server_first = match str::from_utf8(data) {
+            Some(s) => s,
+            None => return Err(PgConnectBadResponse),
+        };
+
+        let mut server_nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+        for attr in server_first.split(',') {
+            if attr.starts_with("r=") {
+                server_nonce = Some(attr.slice_from(2));
+            } else if attr.starts_with("s=") {
+                salt = attr.slice_from(2).from_base64().ok();
+            } else if attr.starts_with("i=") {
+                iterations = FromStr::from_str(attr.slice_from(2));
+            }
+        }
+
+        let (server_nonce, salt, iterations): (&str, Vec<u8>, uint) =
+            match (server_nonce, salt, iterations) {
+                (Some(n), Some(s), Some(i)) if i > 0 => (n, s, i),
+                _ => return Err(PgConnectBadResponse),
+            };
+
+        // The server nonce must extend the one we sent
+        if !server_nonce.starts_with(nonce.as_slice()) ||
+                server_nonce.len() == nonce.len() {
+            return Err(PgConnectBadResponse);
+        }
+
+        let salted_password = pbkdf2(self.password.as_slice(),
+                                     salt.as_slice(),
+                                     iterations);
+
+        let client_key = hmac(salted_password.as_slice(), b"Client Key");
+        let hasher = Hasher::new(SHA256);
+        hasher.update(client_key.as_slice());
+        let stored_key = hasher.final();
+
+        let client_final_without_proof =
+            format!("c={},r={}",
+                    GS2_HEADER.as_bytes().to_base64(STANDARD),
+                    server_nonce);
+        let auth_message = format!("{},{},{}", client_first_bare,
+                                   server_first, client_final_without_proof);
+
+        let client_signature = hmac(stored_key.as_slice(),
+                                    auth_message.as_bytes());
+        let proof: Vec<u8> = client_key.iter()
+            .zip(client_signature.iter())
+            .map(|(a, b)| *a ^ *b)
+            .collect();
+
+        let server_key = hmac(salted_password.as_slice(), b"Server Key");
+        let server_signature = hmac(server_key.as_slice(),
+                                    auth_message.as_bytes());
+
+        self.message = format!("{},p={}", client_final_without_proof,
+                               proof.as_slice().to_base64(STANDARD));
+        self.state = Finish(server_signature);
+        Ok(())
+    }
+
+    /// Verifies the server-final-message.
+    pub fn finish(&mut self, data: &[u8]) -> Result<(), PostgresConnectError> {
+        let signature = match self.state {
+            Finish(ref signature) => signature.clone(),
+            _ => return Err(PgConnectBadResponse),
+        };
+        self.state = Done;
+
+        let server_final = match str::from_utf8(data) {
+            Some(s) => s,
+            None => return Err(PgConnectBadResponse),
+        };
+
+        let verifier = match server_final.split(',').next() {
+            Some(v) if v.starts_with("v=") => v.slice_from(2),
+            _ => return Err(PgConnectBadResponse),
+        };
+
+        match verifier.from_base64() {
+            Ok(ref verifier) if *verifier == signature => Ok(()),
+            _ => Err(PgConnectBadResponse),
+        }
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut hmac = HMAC(SHA256, key);
+    hmac.update(data);
+    hmac.final()
+}
+
+// PBKDF2 with HMAC-SHA256, producing a single block of output
+fn pbkdf2(password: &[u8], salt: &[u8], iterations: uint) -> Vec<u8> {
+    let mut input = Vec::from_slice(salt);
+    input.push_all([0, 0, 0, 1]);
+
+    let mut prev = hmac(password, input.as_slice());
+    let mut result = prev.clone();
+    for _ in range(1, iterations) {
+        prev = hmac(password, prev.as_slice());
+        for (r, p) in result.mut_iter().zip(prev.iter()) {
+            *r ^= *p;
+        }
+    }
+    result
+}
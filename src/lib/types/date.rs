@@ -0,0 +1,81 @@
+//! A calendar date type
+
+use std::fmt;
+
+/// A date in the proleptic Gregorian calendar, with no time zone.
+///
+/// This corresponds to the Postgres `DATE` type.
+#[deriving(PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct Date {
+    days: i32,
+}
+
+impl Date {
+    /// Creates a new date from a year, month and day.
+    ///
+    /// Months and days are 1-indexed. Returns `None` if the date is invalid,
+    /// for example the 29th of February in a year which is not a leap year.
+    pub fn from_ymd(year: i32, month: uint, day: uint) -> Option<Date> {
+        if month < 1 || month > 12 || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        // Shift the year to start in March so the leap day falls at its end
+        let year = if month <= 2 { year as i64 - 1 } else { year as i64 };
+        let era = (if year >= 0 { year } else { year - 399 }) / 400;
+        let year_of_era = year - era * 400;
+        let month = ((month + 9) % 12) as i64;
+        let day_of_year = (153 * month + 2) / 5 + day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100
+            + day_of_year;
+
+        Some(Date { days: (era * 146097 + day_of_era - 719468) as i32 })
+    }
+
+    /// Creates a new date from a number of days since 1970-01-01.
+    pub fn from_days(days: i32) -> Date {
+        Date { days: days }
+    }
+
+    /// Returns the number of days since 1970-01-01.
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    /// Returns the year, month and day of this date.
+    pub fn ymd(&self) -> (i32, uint, uint) {
+        let days = self.days as i64 + 719468;
+        let era = (if days >= 0 { days } else { days - 146096 }) / 146097;
+        let day_of_era = days - era * 146097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+                           - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era
+            - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        (year as i32, month as uint, day as uint)
+    }
+}
+
+impl fmt::Show for Date {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(fmt, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i32, month: uint) -> uint {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
@@ -0,0 +1,75 @@
+//! Geometric types
+
+/// A point on a plane.
+///
+/// This corresponds to the Postgres `POINT` type.
+#[deriving(PartialEq, Clone, Show)]
+pub struct Point {
+    /// The x coordinate
+    pub x: f64,
+    /// The y coordinate
+    pub y: f64,
+}
+
+impl Point {
+    /// Creates a new point.
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x: x, y: y }
+    }
+}
+
+/// A finite line segment.
+///
+/// This corresponds to the Postgres `LSEG` type.
+#[deriving(PartialEq, Clone, Show)]
+pub struct LineSegment {
+    /// The first end point of the segment
+    pub start: Point,
+    /// The second end point of the segment
+    pub end: Point,
+}
+
+/// A sequence of connected points.
+///
+/// This corresponds to the Postgres `PATH` type.
+#[deriving(PartialEq, Clone, Show)]
+pub struct Path {
+    /// If true, the last point is connected back to the first
+    pub closed: bool,
+    /// The points making up the path
+    pub points: Vec<Point>,
+}
+
+/// A closed polygon.
+///
+/// This corresponds to the Postgres `POLYGON` type.
+#[deriving(PartialEq, Clone, Show)]
+pub struct Polygon {
+    /// The vertices of the polygon
+    pub points: Vec<Point>,
+}
+
+impl Polygon {
+    /// Returns the lower left and upper right corners of the smallest box
+    /// containing the polygon, or `None` if it has no vertices.
+    ///
+    /// The server computes the same box when it receives a polygon, so it is
+    /// not sent over the wire.
+    pub fn bounding_box(&self) -> Option<(Point, Point)> {
+        let mut points = self.points.iter();
+        let first = match points.next() {
+            Some(first) => first,
+            None => return None,
+        };
+
+        let mut low = first.clone();
+        let mut high = first.clone();
+        for point in points {
+            low.x = low.x.min(point.x);
+            low.y = low.y.min(point.y);
+            high.x = high.x.max(point.x);
+            high.y = high.y.max(point.y);
+        }
+        Some((low, high))
+    }
+}
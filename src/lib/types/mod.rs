@@ -5,17 +5,32 @@ use uuid::Uuid;
 use serialize::json;
 use serialize::json::Json;
 use std::collections::HashMap;
-use std::io::{MemWriter, BufReader};
+use std::fmt;
+use std::from_str::FromStr;
+use std::collections::bitv::Bitv;
+use std::io::{MemWriter, BufReader, IoError, EndOfFile};
 use std::io::util::LimitReader;
+use std::time::Duration;
 use time::Timespec;
 
 use PostgresResult;
-use error::{PgWrongType, PgStreamError, PgWasNull, PgBadData};
+use error::{PostgresError, PgWrongType, PgStreamError, PgWasNull, PgBadData,
+            PgBadFieldLength};
 use types::array::{Array, ArrayBase, DimensionInfo};
-use types::range::{RangeBound, Inclusive, Exclusive, Range};
+use types::date::Date;
+use types::time_of_day::{Time, TimeTz};
+use types::geometry::{Point, LineSegment, Path, Polygon};
+use types::record::Record;
+use types::numeric::{Numeric, NumericValue, NumericNaN, NumericInfinity, NumericNegInfinity};
+use types::range::{RangeBound, Inclusive, Exclusive, Range, Normalizable, TextRange};
 
 pub mod array;
+pub mod date;
+pub mod geometry;
+pub mod numeric;
 pub mod range;
+pub mod record;
+pub mod time_of_day;
 
 /// A Postgres OID
 pub type Oid = u32;
@@ -27,10 +42,18 @@ static CHAROID: Oid = 18;
 static NAMEOID: Oid = 19;
 static INT8OID: Oid = 20;
 static INT2OID: Oid = 21;
+static INT2VECTOROID: Oid = 22;
 static INT4OID: Oid = 23;
 static TEXTOID: Oid = 25;
+static OIDOID: Oid = 26;
+static OIDVECTOROID: Oid = 30;
 static JSONOID: Oid = 114;
+static XMLOID: Oid = 142;
 static JSONARRAYOID: Oid = 199;
+static POINTOID: Oid = 600;
+static LSEGOID: Oid = 601;
+static PATHOID: Oid = 602;
+static POLYGONOID: Oid = 604;
 static FLOAT4OID: Oid = 700;
 static FLOAT8OID: Oid = 701;
 static BOOLARRAYOID: Oid = 1000;
@@ -47,10 +70,22 @@ static FLOAT4ARRAYOID: Oid = 1021;
 static FLAOT8ARRAYOID: Oid = 1022;
 static BPCHAROID: Oid = 1042;
 static VARCHAROID: Oid = 1043;
+static DATEOID: Oid = 1082;
+static TIMEOID: Oid = 1083;
 static TIMESTAMPOID: Oid = 1114;
 static TIMESTAMPARRAYOID: Oid = 1115;
+static DATEARRAYOID: Oid = 1182;
 static TIMESTAMPZOID: Oid = 1184;
 static TIMESTAMPZARRAYOID: Oid = 1185;
+static INTERVALOID: Oid = 1186;
+static INTERVALARRAYOID: Oid = 1187;
+static TIMETZOID: Oid = 1266;
+static BITOID: Oid = 1560;
+static BITARRAYOID: Oid = 1561;
+static VARBITOID: Oid = 1562;
+static VARBITARRAYOID: Oid = 1563;
+static NUMERICOID: Oid = 1700;
+static RECORDOID: Oid = 2249;
 static UUIDOID: Oid = 2950;
 static UUIDARRAYOID: Oid = 2951;
 static INT4RANGEOID: Oid = 3904;
@@ -59,14 +94,20 @@ static TSRANGEOID: Oid = 3908;
 static TSRANGEARRAYOID: Oid = 3909;
 static TSTZRANGEOID: Oid = 3910;
 static TSTZRANGEARRAYOID: Oid = 3911;
+static DATERANGEOID: Oid = 3912;
+static DATERANGEARRAYOID: Oid = 3913;
 static INT8RANGEOID: Oid = 3926;
 static INT8RANGEARRAYOID: Oid = 3927;
+static JSONBOID: Oid = 3802;
+static JSONBARRAYOID: Oid = 3807;
 
 static USEC_PER_SEC: i64 = 1_000_000;
 static NSEC_PER_USEC: i64 = 1_000;
 
 // Number of seconds from 1970-01-01 to 2000-01-01
 static TIME_SEC_CONVERSION: i64 = 946684800;
+// Number of days from 1970-01-01 to 2000-01-01
+static DATE_DAY_CONVERSION: i32 = 10957;
 
 static RANGE_UPPER_UNBOUNDED: i8 = 0b0001_0000;
 static RANGE_LOWER_UNBOUNDED: i8 = 0b0000_1000;
@@ -74,6 +115,16 @@ static RANGE_UPPER_INCLUSIVE: i8 = 0b0000_0100;
 static RANGE_LOWER_INCLUSIVE: i8 = 0b0000_0010;
 static RANGE_EMPTY: i8           = 0b0000_0001;
 
+static NUMERIC_POS: u16  = 0x0000;
+static NUMERIC_NEG: u16  = 0x4000;
+static NUMERIC_NAN: u16  = 0xC000;
+static NUMERIC_PINF: u16 = 0xD000;
+static NUMERIC_NINF: u16 = 0xF000;
+// NUMERIC digits are stored in base 10000
+static NUMERIC_DEC_DIGITS: uint = 4;
+
+static JSONB_VERSION: u8 = 1;
+
 macro_rules! make_postgres_type(
     ($(#[$doc:meta] $oid:ident => $variant:ident $(member $member:ident)*),+) => (
         /// A Postgres type
@@ -89,6 +140,15 @@ macro_rules! make_postgres_type(
                 pub name: String,
                 /// The OID of the type
                 pub oid: Oid
+            },
+            /// An array of a type without built in support, such as an enum
+            PgUnknownArray {
+                /// The name of the array type
+                pub name: String,
+                /// The OID of the array type
+                pub oid: Oid,
+                /// The type of the array's elements
+                pub member: Box<PostgresType>
             }
         }
 
@@ -106,16 +166,27 @@ macro_rules! make_postgres_type(
             pub fn to_oid(&self) -> Oid {
                 match *self {
                     $($variant => $oid,)+
-                    PgUnknownType { oid, .. } => oid
+                    PgUnknownType { oid, .. } => oid,
+                    PgUnknownArray { oid, .. } => oid
                 }
             }
 
-            fn member_type(&self) -> PostgresType {
+            /// Returns the type of the elements of `self` if it is an array
+            /// type.
+            pub fn element_type(&self) -> Option<PostgresType> {
                 match *self {
                     $(
-                        $($variant => $member,)*
+                        $($variant => Some($member),)*
                     )+
-                    _ => unreachable!()
+                    PgUnknownArray { ref member, .. } => Some((**member).clone()),
+                    _ => None
+                }
+            }
+
+            fn member_type(&self) -> PostgresType {
+                match self.element_type() {
+                    Some(ty) => ty,
+                    None => unreachable!()
                 }
             }
 
@@ -124,7 +195,7 @@ macro_rules! make_postgres_type(
                 match *self {
                     PgUnknownType { name: ref name, .. }
                         if "hstore" == name.as_slice() => Binary,
-                    PgUnknownType { .. } => Text,
+                    PgUnknownType { .. } | PgUnknownArray { .. } => Text,
                     _ => Binary
                 }
             }
@@ -145,14 +216,30 @@ make_postgres_type!(
     INT8OID => PgInt8,
     #[doc="INT2/SMALLINT"]
     INT2OID => PgInt2,
+    #[doc="INT2VECTOR"]
+    INT2VECTOROID => PgInt2Vector member PgInt2,
     #[doc="INT4/INT"]
     INT4OID => PgInt4,
     #[doc="TEXT"]
     TEXTOID => PgText,
+    #[doc="OID"]
+    OIDOID => PgOid,
+    #[doc="OIDVECTOR"]
+    OIDVECTOROID => PgOidVector member PgOid,
     #[doc="JSON"]
     JSONOID => PgJson,
     #[doc="JSON[]"]
     JSONARRAYOID => PgJsonArray member PgJson,
+    #[doc="XML"]
+    XMLOID => PgXml,
+    #[doc="POINT"]
+    POINTOID => PgPoint,
+    #[doc="LSEG"]
+    LSEGOID => PgLseg,
+    #[doc="PATH"]
+    PATHOID => PgPath,
+    #[doc="POLYGON"]
+    POLYGONOID => PgPolygon,
     #[doc="FLOAT4/REAL"]
     FLOAT4OID => PgFloat4,
     #[doc="FLOAT8/DOUBLE PRECISION"]
@@ -193,6 +280,30 @@ make_postgres_type!(
     BPCHAROID => PgCharN,
     #[doc="VARCHAR/CHARACTER VARYING"]
     VARCHAROID => PgVarchar,
+    #[doc="DATE"]
+    DATEOID => PgDate,
+    #[doc="TIME"]
+    TIMEOID => PgTime,
+    #[doc="TIME WITH TIME ZONE"]
+    TIMETZOID => PgTimeTz,
+    #[doc="NUMERIC"]
+    NUMERICOID => PgNumeric,
+    #[doc="RECORD"]
+    RECORDOID => PgRecord,
+    #[doc="DATE[]"]
+    DATEARRAYOID => PgDateArray member PgDate,
+    #[doc="INTERVAL"]
+    INTERVALOID => PgInterval,
+    #[doc="INTERVAL[]"]
+    INTERVALARRAYOID => PgIntervalArray member PgInterval,
+    #[doc="BIT"]
+    BITOID => PgBit,
+    #[doc="BIT[]"]
+    BITARRAYOID => PgBitArray member PgBit,
+    #[doc="VARBIT/BIT VARYING"]
+    VARBITOID => PgVarbit,
+    #[doc="VARBIT[]"]
+    VARBITARRAYOID => PgVarbitArray member PgVarbit,
     #[doc="UUID"]
     UUIDOID => PgUuid,
     #[doc="UUID[]"]
@@ -209,13 +320,22 @@ make_postgres_type!(
     TSTZRANGEOID => PgTstzRange,
     #[doc="TSTZRANGE[]"]
     TSTZRANGEARRAYOID => PgTstzRangeArray member PgTstzRange,
+    #[doc="DATERANGE"]
+    DATERANGEOID => PgDateRange,
+    #[doc="DATERANGE[]"]
+    DATERANGEARRAYOID => PgDateRangeArray member PgDateRange,
     #[doc="INT8RANGE"]
     INT8RANGEOID => PgInt8Range,
     #[doc="INT8RANGE[]"]
-    INT8RANGEARRAYOID => PgInt8RangeArray member PgInt8Range
+    INT8RANGEARRAYOID => PgInt8RangeArray member PgInt8Range,
+    #[doc="JSONB"]
+    JSONBOID => PgJsonb,
+    #[doc="JSONB[]"]
+    JSONBARRAYOID => PgJsonbArray member PgJsonb
 )
 
 /// The wire format of a Postgres value
+#[deriving(PartialEq, Eq, Clone, Show)]
 pub enum Format {
     /// A user-readable string format
     Text = 0,
@@ -233,12 +353,33 @@ macro_rules! check_types(
 )
 
 /// A trait for types that can be created from a Postgres value
+///
+/// `BIGINT` and `BIGSERIAL` values, including those returned by `currval()`
+/// and `lastval()`, should be read as `i64`. They are never converted through
+/// a floating point type.
+///
+/// Integer types must match exactly: reading a `SMALLINT` column as an `i32`,
+/// for example, returns a `PgWrongType` error. `PostgresRow::get_as_i64` can
+/// be used to read any integer column.
 pub trait FromSql {
     /// Creates a new value of this type from a buffer of Postgres data.
     ///
     /// If the value was `NULL`, the buffer will be `None`.
     fn from_sql(ty: &PostgresType, raw: &Option<Vec<u8>>)
             -> PostgresResult<Self>;
+
+    /// Creates a new value of this type from a Postgres value in the text
+    /// format, as returned by the simple query protocol.
+    ///
+    /// If the value was `NULL`, the string will be `None`. The default
+    /// implementation passes the UTF-8 bytes of the string to `from_sql`,
+    /// which is correct for types whose binary format is the same as their
+    /// text format.
+    fn from_sql_text(ty: &PostgresType, raw: &Option<String>)
+            -> PostgresResult<Self> {
+        let raw = raw.as_ref().map(|s| Vec::from_slice(s.as_bytes()));
+        FromSql::from_sql(ty, &raw)
+    }
 }
 
 #[doc(hidden)]
@@ -256,9 +397,15 @@ macro_rules! raw_from_impl(
     )
 )
 
+// The server only ever sends 0 or 1, so any other byte means the value was
+// not really a BOOL.
 impl RawFromSql for bool {
     fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<bool> {
-        Ok((try_pg!(raw.read_u8())) != 0)
+        match try_pg!(raw.read_u8()) {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(PgBadData),
+        }
     }
 }
 
@@ -274,10 +421,23 @@ impl RawFromSql for String {
     }
 }
 
+// A single character, as stored in a CHAR(1) column
+impl RawFromSql for char {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<char> {
+        let s: String = try!(RawFromSql::raw_from_sql(raw));
+        char_from_text(s.as_slice())
+    }
+}
+
 raw_from_impl!(i8, read_i8)
 raw_from_impl!(i16, read_be_i16)
 raw_from_impl!(i32, read_be_i32)
+// INT8 values are read directly as big-endian integers, so the full range of
+// i64 (including BIGSERIAL sequence values above 2^53) is preserved exactly.
 raw_from_impl!(i64, read_be_i64)
+raw_from_impl!(u32, read_be_u32)
+// Floats are sent as their raw IEEE 754 bits, so NaN, the infinities and
+// negative zero are preserved exactly.
 raw_from_impl!(f32, read_be_f32)
 raw_from_impl!(f64, read_be_f64)
 
@@ -296,6 +456,21 @@ impl RawFromSql for Timespec {
     }
 }
 
+// An interval is encoded as microseconds, days and months. Days are treated
+// as exactly 24 hours, but months have no fixed length, so an interval with
+// a month component is rejected rather than approximated.
+impl RawFromSql for Duration {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Duration> {
+        let usecs = try_pg!(raw.read_be_i64());
+        let days = try_pg!(raw.read_be_i32());
+        let months = try_pg!(raw.read_be_i32());
+        if months != 0 {
+            return Err(PgIntervalHasMonths);
+        }
+        Ok(Duration::days(days as i64) + Duration::microseconds(usecs))
+    }
+}
+
 impl RawFromSql for Uuid {
     fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Uuid> {
         match Uuid::from_bytes(try_pg!(raw.read_to_end()).as_slice()) {
@@ -305,6 +480,151 @@ impl RawFromSql for Uuid {
     }
 }
 
+impl RawFromSql for Bitv {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Bitv> {
+        let len = try_pg!(raw.read_be_i32());
+        if len < 0 {
+            return Err(PgBadData);
+        }
+        let len = len as uint;
+        let bytes = try_pg!(raw.read_to_end());
+        if bytes.len() != (len + 7) / 8 {
+            return Err(PgBadData);
+        }
+        // Only the first `len` bits are significant, so drop the padding
+        let mut bitv = Bitv::from_bytes(bytes.as_slice());
+        bitv.truncate(len);
+        Ok(bitv)
+    }
+}
+
+impl RawFromSql for Date {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Date> {
+        let days = try_pg!(raw.read_be_i32());
+        Ok(Date::from_days(days + DATE_DAY_CONVERSION))
+    }
+}
+
+impl RawFromSql for Time {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Time> {
+        match Time::from_usecs(try_pg!(raw.read_be_i64())) {
+            Some(time) => Ok(time),
+            None => Err(PgBadData),
+        }
+    }
+}
+
+impl RawFromSql for Numeric {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Numeric> {
+        let ndigits = try_pg!(raw.read_be_i16());
+        let weight = try_pg!(raw.read_be_i16()) as int;
+        let sign = try_pg!(raw.read_be_u16());
+        let dscale = try_pg!(raw.read_be_u16()) as uint;
+        let mut digits = vec![];
+        for _ in range(0, ndigits) {
+            let digit = try_pg!(raw.read_be_i16());
+            if digit < 0 || digit >= 10000 {
+                return Err(PgBadData);
+            }
+            digits.push(digit);
+        }
+
+        let negative = match sign {
+            NUMERIC_POS => false,
+            NUMERIC_NEG => true,
+            NUMERIC_NAN => return Ok(NumericNaN),
+            NUMERIC_PINF => return Ok(NumericInfinity),
+            NUMERIC_NINF => return Ok(NumericNegInfinity),
+            _ => return Err(PgBadData),
+        };
+
+        let digit = |i: int| -> i16 {
+            if i >= 0 && i < digits.len() as int { digits[i as uint] } else { 0 }
+        };
+
+        let mut s = String::new();
+        if negative {
+            s.push_char('-');
+        }
+        if weight < 0 {
+            s.push_char('0');
+        } else {
+            s.push_str(digit(0).to_str().as_slice());
+            for i in range(1, weight + 1) {
+                s.push_str(format!("{:04}", digit(i)).as_slice());
+            }
+        }
+
+        if dscale > 0 {
+            let mut frac = String::new();
+            let mut i = weight + 1;
+            while frac.len() < dscale {
+                frac.push_str(format!("{:04}", digit(i)).as_slice());
+                i += 1;
+            }
+            s.push_char('.');
+            s.push_str(frac.as_slice().slice_to(dscale));
+        }
+
+        Ok(NumericValue(s))
+    }
+}
+
+// Postgres stores the zone as seconds west of UTC
+impl RawFromSql for TimeTz {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<TimeTz> {
+        let time = try!(RawFromSql::raw_from_sql(raw));
+        let zone = try_pg!(raw.read_be_i32());
+        Ok(TimeTz { time: time, offset: -zone })
+    }
+}
+
+impl RawFromSql for Point {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Point> {
+        let x = try_pg!(raw.read_be_f64());
+        let y = try_pg!(raw.read_be_f64());
+        Ok(Point::new(x, y))
+    }
+}
+
+impl RawFromSql for LineSegment {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<LineSegment> {
+        Ok(LineSegment {
+            start: try!(RawFromSql::raw_from_sql(raw)),
+            end: try!(RawFromSql::raw_from_sql(raw)),
+        })
+    }
+}
+
+fn read_points<R: Reader>(raw: &mut R) -> PostgresResult<Vec<Point>> {
+    let npts = try_pg!(raw.read_be_i32());
+    if npts < 0 {
+        return Err(PgBadData);
+    }
+    let mut points = Vec::new();
+    for _ in range(0, npts) {
+        points.push(try!(RawFromSql::raw_from_sql(raw)));
+    }
+    Ok(points)
+}
+
+impl RawFromSql for Path {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Path> {
+        let closed = try_pg!(raw.read_u8()) != 0;
+        Ok(Path {
+            closed: closed,
+            points: try!(read_points(raw)),
+        })
+    }
+}
+
+// The bounding box of a polygon is not sent over the wire
+impl RawFromSql for Polygon {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Polygon> {
+        Ok(Polygon { points: try!(read_points(raw)) })
+    }
+}
+
 macro_rules! from_range_impl(
     ($t:ty) => (
         impl RawFromSql for Range<$t> {
@@ -356,6 +676,7 @@ macro_rules! from_range_impl(
 from_range_impl!(i32)
 from_range_impl!(i64)
 from_range_impl!(Timespec)
+from_range_impl!(Date)
 
 impl RawFromSql for Json {
     fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Json> {
@@ -370,7 +691,7 @@ macro_rules! from_map_impl(
                     -> PostgresResult<Option<$t>> {
                 check_types!($($expected)|+, ty)
                 match *raw {
-                    Some(ref buf) => ($blk)(buf).map(|ok| Some(ok)),
+                    Some(ref buf) => ($blk)(ty, buf).map(|ok| Some(ok)),
                     None => Ok(None)
                 }
             }
@@ -388,38 +709,184 @@ macro_rules! from_map_impl(
                 }
             }
         }
+    );
+    ($($expected:pat)|+, $t:ty, $blk:expr, $text:expr) => (
+        impl FromSql for Option<$t> {
+            fn from_sql(ty: &PostgresType, raw: &Option<Vec<u8>>)
+                    -> PostgresResult<Option<$t>> {
+                check_types!($($expected)|+, ty)
+                match *raw {
+                    Some(ref buf) => ($blk)(ty, buf).map(|ok| Some(ok)),
+                    None => Ok(None)
+                }
+            }
+
+            fn from_sql_text(ty: &PostgresType, raw: &Option<String>)
+                    -> PostgresResult<Option<$t>> {
+                check_types!($($expected)|+, ty)
+                match *raw {
+                    Some(ref s) => ($text)(s.as_slice()).map(|ok| Some(ok)),
+                    None => Ok(None)
+                }
+            }
+        }
+
+        impl FromSql for $t {
+            fn from_sql(ty: &PostgresType, raw: &Option<Vec<u8>>)
+                    -> PostgresResult<$t> {
+                // FIXME when you can specify Self types properly
+                let ret: PostgresResult<Option<$t>> = FromSql::from_sql(ty, raw);
+                match ret {
+                    Ok(Some(val)) => Ok(val),
+                    Ok(None) => Err(PgWasNull),
+                    Err(err) => Err(err)
+                }
+            }
+
+            fn from_sql_text(ty: &PostgresType, raw: &Option<String>)
+                    -> PostgresResult<$t> {
+                let ret: PostgresResult<Option<$t>> = FromSql::from_sql_text(ty, raw);
+                match ret {
+                    Ok(Some(val)) => Ok(val),
+                    Ok(None) => Err(PgWasNull),
+                    Err(err) => Err(err)
+                }
+            }
+        }
     )
 )
 
+// Each value is decoded from a buffer holding exactly that value, so a decoder
+// which runs out of data or leaves some of it unread has misread the value.
+fn check_field_length<T>(ty: &PostgresType, result: PostgresResult<T>,
+                         remaining: uint) -> PostgresResult<T> {
+    match result {
+        Ok(_) if remaining != 0 => Err(bad_field_length(ty)),
+        Err(PgStreamError(IoError { kind: EndOfFile, .. })) => Err(bad_field_length(ty)),
+        result => result,
+    }
+}
+
+fn bad_field_length(ty: &PostgresType) -> PostgresError {
+    PgBadFieldLength {
+        column: None,
+        ty: ty.clone(),
+    }
+}
+
 macro_rules! from_raw_from_impl(
     ($($expected:pat)|+, $t:ty) => (
-        from_map_impl!($($expected)|+, $t, |buf: &Vec<u8>| {
+        from_map_impl!($($expected)|+, $t, |ty: &PostgresType, buf: &Vec<u8>| {
             let mut reader = BufReader::new(buf.as_slice());
-            RawFromSql::raw_from_sql(&mut reader)
+            let result = RawFromSql::raw_from_sql(&mut reader);
+            let remaining = buf.len() - try_pg!(reader.tell()) as uint;
+            check_field_length(ty, result, remaining)
         })
+    );
+    ($($expected:pat)|+, $t:ty, $text:expr) => (
+        from_map_impl!($($expected)|+, $t, |ty: &PostgresType, buf: &Vec<u8>| {
+            let mut reader = BufReader::new(buf.as_slice());
+            let result = RawFromSql::raw_from_sql(&mut reader);
+            let remaining = buf.len() - try_pg!(reader.tell()) as uint;
+            check_field_length(ty, result, remaining)
+        }, $text)
     )
 )
 
-from_raw_from_impl!(PgBool, bool)
+// In the binary format, JSONB values are the text of the document prefixed
+// with a format version byte.
+fn read_jsonb_version<R: Reader>(ty: &PostgresType, raw: &mut R) -> PostgresResult<()> {
+    if *ty == PgJsonb && try_pg!(raw.read_u8()) != JSONB_VERSION {
+        return Err(PgBadData);
+    }
+    Ok(())
+}
+
+fn bool_from_text(s: &str) -> PostgresResult<bool> {
+    match s {
+        "t" => Ok(true),
+        "f" => Ok(false),
+        _ => Err(PgBadData),
+    }
+}
+
+fn int_from_text<T: FromStr>(s: &str) -> PostgresResult<T> {
+    match from_str(s) {
+        Some(val) => Ok(val),
+        None => Err(PgBadData),
+    }
+}
+
+// Postgres spells the special values differently than Rust does
+fn float_from_text<T: Float+FromStr>(s: &str) -> PostgresResult<T> {
+    match s {
+        "NaN" => Ok(Float::nan()),
+        "Infinity" => Ok(Float::infinity()),
+        "-Infinity" => Ok(Float::neg_infinity()),
+        s => int_from_text(s),
+    }
+}
+
+fn char_from_text(s: &str) -> PostgresResult<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(PgBadData)
+    }
+}
+
+from_raw_from_impl!(PgBool, bool, bool_from_text)
 from_raw_from_impl!(PgByteA, Vec<u8>)
-from_raw_from_impl!(PgVarchar | PgText | PgCharN | PgName, String)
+// XML documents are sent as text in both formats and are validated by the
+// server, so they are treated as plain strings. JSON documents are returned as
+// their text without being parsed.
+from_map_impl!(PgVarchar | PgText | PgCharN | PgName | PgXml | PgJson | PgJsonb, String,
+               |ty: &PostgresType, buf: &Vec<u8>| {
+    let mut reader = BufReader::new(buf.as_slice());
+    try!(read_jsonb_version(ty, &mut reader));
+    let result = RawFromSql::raw_from_sql(&mut reader);
+    let remaining = buf.len() - try_pg!(reader.tell()) as uint;
+    check_field_length(ty, result, remaining)
+}, |s: &str| Ok(s.to_str()))
+from_raw_from_impl!(PgCharN | PgVarchar | PgText, char, char_from_text)
 from_raw_from_impl!(PgChar, i8)
-from_raw_from_impl!(PgInt2, i16)
-from_raw_from_impl!(PgInt4, i32)
-from_raw_from_impl!(PgInt8, i64)
-from_raw_from_impl!(PgFloat4, f32)
-from_raw_from_impl!(PgFloat8, f64)
+from_raw_from_impl!(PgInt2, i16, int_from_text)
+from_raw_from_impl!(PgInt4, i32, int_from_text)
+from_raw_from_impl!(PgInt8, i64, int_from_text)
+from_raw_from_impl!(PgOid, u32, int_from_text)
+from_raw_from_impl!(PgFloat4, f32, float_from_text)
+from_raw_from_impl!(PgFloat8, f64, float_from_text)
 from_raw_from_impl!(PgUuid, Uuid)
-from_raw_from_impl!(PgJson, Json)
+from_raw_from_impl!(PgInterval, Duration)
+from_raw_from_impl!(PgBit | PgVarbit, Bitv)
+from_map_impl!(PgJson | PgJsonb, Json, |ty: &PostgresType, buf: &Vec<u8>| {
+    let mut reader = BufReader::new(buf.as_slice());
+    try!(read_jsonb_version(ty, &mut reader));
+    let result = RawFromSql::raw_from_sql(&mut reader);
+    let remaining = buf.len() - try_pg!(reader.tell()) as uint;
+    check_field_length(ty, result, remaining)
+})
+from_raw_from_impl!(PgPoint, Point)
+from_raw_from_impl!(PgLseg, LineSegment)
+from_raw_from_impl!(PgPath, Path)
+from_raw_from_impl!(PgPolygon, Polygon)
 
 from_raw_from_impl!(PgTimestamp | PgTimestampTZ, Timespec)
+from_raw_from_impl!(PgDate, Date)
+from_raw_from_impl!(PgTime, Time)
+from_raw_from_impl!(PgTimeTz, TimeTz)
+from_raw_from_impl!(PgNumeric, Numeric)
+from_raw_from_impl!(PgRecord, Record)
 from_raw_from_impl!(PgInt4Range, Range<i32>)
 from_raw_from_impl!(PgInt8Range, Range<i64>)
+// TSRANGE and TSTZRANGE share a wire format. The type of a parameter is always
+// the one reported by the server, so the two are never confused on the wire.
 from_raw_from_impl!(PgTsRange | PgTstzRange, Range<Timespec>)
+from_raw_from_impl!(PgDateRange, Range<Date>)
 
 macro_rules! from_array_impl(
     ($($oid:ident)|+, $t:ty) => (
-        from_map_impl!($($oid)|+, ArrayBase<Option<$t>>, |buf: &Vec<u8>| {
+        from_map_impl!($($oid)|+, ArrayBase<Option<$t>>, |ty: &PostgresType, buf: &Vec<u8>| {
             let mut rdr = BufReader::new(buf.as_slice());
 
             let ndim = try_pg!(rdr.read_be_i32()) as uint;
@@ -442,8 +909,10 @@ macro_rules! from_array_impl(
                     elements.push(None);
                 } else {
                     let mut limit = LimitReader::new(rdr.by_ref(), len as uint);
-                    elements.push(Some(try!(RawFromSql::raw_from_sql(&mut limit))));
-                    assert!(limit.limit() == 0);
+                    let result = RawFromSql::raw_from_sql(&mut limit);
+                    let remaining = limit.limit();
+                    elements.push(Some(try!(check_field_length(&ty.member_type(),
+                                                               result, remaining))));
                 }
             }
 
@@ -460,14 +929,165 @@ from_array_impl!(PgInt4Array, i32)
 from_array_impl!(PgTextArray | PgCharNArray | PgVarcharArray | PgNameArray, String)
 from_array_impl!(PgInt8Array, i64)
 from_array_impl!(PgTimestampArray | PgTimestampTZArray, Timespec)
+from_array_impl!(PgDateArray, Date)
 from_array_impl!(PgJsonArray, Json)
 from_array_impl!(PgFloat4Array, f32)
 from_array_impl!(PgFloat8Array, f64)
 from_array_impl!(PgUuidArray, Uuid)
+from_array_impl!(PgIntervalArray, Duration)
+from_array_impl!(PgBitArray | PgVarbitArray, Bitv)
 from_array_impl!(PgInt4RangeArray, Range<i32>)
 from_array_impl!(PgTsRangeArray | PgTstzRangeArray, Range<Timespec>)
+from_array_impl!(PgDateRangeArray, Range<Date>)
 from_array_impl!(PgInt8RangeArray, Range<i64>)
 
+// INT2VECTOR and OIDVECTOR values are sent in the binary format of a
+// one-dimensional array, but may never contain NULLs.
+macro_rules! from_vector_impl(
+    ($oid:ident, $t:ty) => (
+        from_map_impl!($oid, Vec<$t>, |ty: &PostgresType, buf: &Vec<u8>| {
+            let mut rdr = BufReader::new(buf.as_slice());
+
+            let ndim = try_pg!(rdr.read_be_i32());
+            let _has_null = try_pg!(rdr.read_be_i32());
+            let _element_type: Oid = try_pg!(rdr.read_be_u32());
+
+            let nele = match ndim {
+                0 => 0,
+                1 => {
+                    let len = try_pg!(rdr.read_be_i32());
+                    let _lower_bound = try_pg!(rdr.read_be_i32());
+                    if len < 0 {
+                        return Err(PgBadData);
+                    }
+                    len as uint
+                }
+                _ => return Err(PgBadData)
+            };
+
+            let mut elements = Vec::new();
+            for _ in range(0, nele) {
+                let len = try_pg!(rdr.read_be_i32());
+                if len < 0 {
+                    return Err(PgBadData);
+                }
+                let mut limit = LimitReader::new(rdr.by_ref(), len as uint);
+                let result = RawFromSql::raw_from_sql(&mut limit);
+                let remaining = limit.limit();
+                elements.push(try!(check_field_length(&ty.member_type(), result,
+                                                      remaining)));
+            }
+
+            Ok(elements)
+        })
+    )
+)
+
+from_vector_impl!(PgInt2Vector, i16)
+from_vector_impl!(PgOidVector, u32)
+
+/// Creates a `Vec` from a one-dimensional Postgres array of type `ty`.
+///
+/// This allows `FromSql` to be implemented for arrays of types whose element
+/// OID is only known at runtime, such as enums. Like other types without built
+/// in support, these arrays are read in the text format, e.g. `{happy,sad}`.
+/// Each element is converted by its own `FromSql::from_sql_text`
+/// implementation, with the array's member type and the element's text
+/// representation. A `NULL` array is returned as `None`, and arrays with more
+/// than one dimension or elements which are not valid UTF-8 result in a
+/// `PgBadData` error.
+pub fn array_from_sql<T: FromSql>(ty: &PostgresType, raw: &Option<Vec<u8>>)
+        -> PostgresResult<Option<Vec<T>>> {
+    let member = match ty.element_type() {
+        Some(member) => member,
+        None => return Err(PgWrongType(ty.clone()))
+    };
+    let buf = match *raw {
+        Some(ref buf) => buf,
+        None => return Ok(None)
+    };
+
+    let mut elements = vec![];
+    for value in try!(read_text_array(buf.as_slice())).move_iter() {
+        let value = match value {
+            Some(value) => Some(try!(String::from_utf8(value).map_err(|_| PgBadData))),
+            None => None
+        };
+        elements.push(try!(FromSql::from_sql_text(&member, &value)));
+    }
+
+    Ok(Some(elements))
+}
+
+fn read_text_array(buf: &[u8]) -> PostgresResult<Vec<Option<Vec<u8>>>> {
+    let mut it = buf.iter().map(|&b| b).peekable();
+
+    // Arrays with a lower bound other than 1 are prefixed by their bounds,
+    // e.g. [0:1]={a,b}
+    if it.peek() == Some(&('[' as u8)) && !it.by_ref().any(|b| b == '=' as u8) {
+        return Err(PgBadData);
+    }
+    if it.next() != Some('{' as u8) {
+        return Err(PgBadData);
+    }
+
+    let mut elements = vec![];
+    if it.peek() == Some(&('}' as u8)) {
+        it.next();
+    } else {
+        loop {
+            let element = match it.peek().map(|&b| b) {
+                Some(b) if b == '{' as u8 => return Err(PgBadData),
+                Some(b) if b == '"' as u8 => {
+                    it.next();
+                    let mut value = vec![];
+                    loop {
+                        match it.next() {
+                            Some(b) if b == '\\' as u8 => match it.next() {
+                                Some(b) => value.push(b),
+                                None => return Err(PgBadData)
+                            },
+                            Some(b) if b == '"' as u8 => break,
+                            Some(b) => value.push(b),
+                            None => return Err(PgBadData)
+                        }
+                    }
+                    Some(value)
+                }
+                _ => {
+                    let mut value = vec![];
+                    loop {
+                        match it.peek() {
+                            Some(&b) if b == ',' as u8 || b == '}' as u8 => break,
+                            Some(&b) => value.push(b),
+                            None => return Err(PgBadData)
+                        }
+                        it.next();
+                    }
+                    // Elements spelling NULL are quoted by the server
+                    if b"NULL" == value.as_slice() {
+                        None
+                    } else {
+                        Some(value)
+                    }
+                }
+            };
+            elements.push(element);
+
+            match it.next() {
+                Some(b) if b == ',' as u8 => {}
+                Some(b) if b == '}' as u8 => break,
+                _ => return Err(PgBadData)
+            }
+        }
+    }
+
+    if it.next().is_some() {
+        return Err(PgBadData);
+    }
+    Ok(elements)
+}
+
 impl FromSql for Option<HashMap<String, Option<String>>> {
     fn from_sql(ty: &PostgresType, raw: &Option<Vec<u8>>)
                 -> PostgresResult<Option<HashMap<String, Option<String>>>> {
@@ -527,11 +1147,62 @@ impl FromSql for HashMap<String, Option<String>> {
 }
 
 /// A trait for types that can be converted into Postgres values
+///
+/// Borrowed `&str` and `&[u8]` values, and `Option`s of them, can be passed
+/// as parameters directly; there is no need to copy them into a `String` or
+/// `Vec<u8>` first.
+///
+/// Parameters are passed as `&ToSql` trait objects, so values of different
+/// types can be mixed in a single parameter list without boxing, for example
+/// `[&1i32, &"hello", &true]`. Implementations must keep the trait object
+/// safe: methods may not be generic or take `Self` by value.
 pub trait ToSql {
     /// Converts the value of `self` into a format appropriate for the Postgres
     /// backend.
     fn to_sql(&self, ty: &PostgresType)
             -> PostgresResult<(Format, Option<Vec<u8>>)>;
+
+    /// Returns the type that should be declared for this value when a
+    /// statement is prepared on its behalf, as `PostgresConnection::execute`
+    /// does.
+    ///
+    /// The default implementation returns `None`, which leaves the server to
+    /// infer the type of the parameter from the query.
+    fn declared_type(&self) -> Option<PostgresType> {
+        None
+    }
+}
+
+/// A `NULL` parameter of a specific type.
+///
+/// The server cannot infer the type of a parameter in some positions, such
+/// as `$1 IS NULL`, and preparing such a query fails with an
+/// `IndeterminateDatatype` error. When a `Null` is passed to
+/// `PostgresConnection::execute` or `PostgresTransaction::execute`, its type
+/// is declared when the query is prepared, which resolves the ambiguity.
+/// Statements prepared ahead of time should declare the type with
+/// `prepare_typed` instead.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use postgres::{PostgresConnection, NoSsl};
+/// # use postgres::types::{Null, PgInt4};
+/// # let conn = PostgresConnection::connect("", &NoSsl).unwrap();
+/// conn.execute("UPDATE foo SET bar = 1 WHERE $1 IS NULL", [&Null(PgInt4)]).unwrap();
+/// ```
+pub struct Null(pub PostgresType);
+
+impl ToSql for Null {
+    fn to_sql(&self, _: &PostgresType)
+            -> PostgresResult<(Format, Option<Vec<u8>>)> {
+        Ok((Text, None))
+    }
+
+    fn declared_type(&self) -> Option<PostgresType> {
+        let Null(ref ty) = *self;
+        Some(ty.clone())
+    }
 }
 
 #[doc(hidden)]
@@ -567,13 +1238,26 @@ impl RawToSql for String {
     }
 }
 
+impl RawToSql for char {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        Ok(try_pg!(w.write_char(*self)))
+    }
+}
+
 raw_to_impl!(i8, write_i8)
 raw_to_impl!(i16, write_be_i16)
 raw_to_impl!(i32, write_be_i32)
 raw_to_impl!(i64, write_be_i64)
+raw_to_impl!(u32, write_be_u32)
 raw_to_impl!(f32, write_be_f32)
 raw_to_impl!(f64, write_be_f64)
 
+impl RawToSql for Date {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        Ok(try_pg!(w.write_be_i32(self.days() - DATE_DAY_CONVERSION)))
+    }
+}
+
 impl RawToSql for Timespec {
     fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
         let t = (self.sec - TIME_SEC_CONVERSION) * USEC_PER_SEC
@@ -582,12 +1266,153 @@ impl RawToSql for Timespec {
     }
 }
 
+impl RawToSql for Duration {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        let (usecs, days) = match self.num_microseconds() {
+            Some(usecs) => (usecs, 0),
+            None => {
+                let days = self.num_days();
+                let usecs = (*self - Duration::days(days)).num_microseconds().unwrap();
+                (usecs, days as i32)
+            }
+        };
+        try_pg!(w.write_be_i64(usecs));
+        try_pg!(w.write_be_i32(days));
+        Ok(try_pg!(w.write_be_i32(0)))
+    }
+}
+
 impl RawToSql for Uuid {
     fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
         Ok(try_pg!(w.write(self.as_bytes())))
     }
 }
 
+impl RawToSql for Time {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        Ok(try_pg!(w.write_be_i64(self.usecs())))
+    }
+}
+
+impl RawToSql for Numeric {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        let s = match *self {
+            NumericValue(ref s) => s.as_slice(),
+            NumericNaN => return write_numeric_special(w, NUMERIC_NAN),
+            NumericInfinity => return write_numeric_special(w, NUMERIC_PINF),
+            NumericNegInfinity => return write_numeric_special(w, NUMERIC_NINF),
+        };
+        // The value may have been constructed directly rather than parsed
+        if from_str::<Numeric>(s).is_none() {
+            return Err(PgBadData);
+        }
+        let (negative, s) = if s.starts_with("-") {
+            (true, s.slice_from(1))
+        } else {
+            (false, s)
+        };
+        let (int, frac) = match s.find('.') {
+            Some(idx) => (s.slice_to(idx), s.slice_from(idx + 1)),
+            None => (s, ""),
+        };
+
+        // Pad both parts out to whole base 10000 digits
+        let int_pad = (NUMERIC_DEC_DIGITS - int.len() % NUMERIC_DEC_DIGITS) % NUMERIC_DEC_DIGITS;
+        let frac_pad = (NUMERIC_DEC_DIGITS - frac.len() % NUMERIC_DEC_DIGITS) % NUMERIC_DEC_DIGITS;
+        let mut padded = String::new();
+        for _ in range(0, int_pad) {
+            padded.push_char('0');
+        }
+        padded.push_str(int);
+        padded.push_str(frac);
+        for _ in range(0, frac_pad) {
+            padded.push_char('0');
+        }
+
+        let mut digits: Vec<i16> = padded.as_bytes().chunks(NUMERIC_DEC_DIGITS).map(|chunk| {
+            chunk.iter().fold(0, |acc, &b| acc * 10 + (b - b'0') as i16)
+        }).collect();
+        let mut weight = ((int.len() + int_pad) / NUMERIC_DEC_DIGITS) as i16 - 1;
+
+        let leading = digits.iter().take_while(|&&d| d == 0).count();
+        digits = Vec::from_slice(digits.slice_from(leading));
+        weight -= leading as i16;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            weight = 0;
+        }
+
+        let sign = if negative && !digits.is_empty() { NUMERIC_NEG } else { NUMERIC_POS };
+        try_pg!(w.write_be_i16(digits.len() as i16));
+        try_pg!(w.write_be_i16(weight));
+        try_pg!(w.write_be_u16(sign));
+        try_pg!(w.write_be_u16(frac.len() as u16));
+        for &digit in digits.iter() {
+            try_pg!(w.write_be_i16(digit));
+        }
+        Ok(())
+    }
+}
+
+// Special values have no digits, and a zero weight and scale
+fn write_numeric_special<W: Writer>(w: &mut W, sign: u16) -> PostgresResult<()> {
+    try_pg!(w.write_be_i16(0));
+    try_pg!(w.write_be_i16(0));
+    try_pg!(w.write_be_u16(sign));
+    Ok(try_pg!(w.write_be_u16(0)))
+}
+
+impl RawToSql for TimeTz {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        try!(self.time.raw_to_sql(w));
+        Ok(try_pg!(w.write_be_i32(-self.offset)))
+    }
+}
+
+impl RawToSql for Point {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        try_pg!(w.write_be_f64(self.x));
+        Ok(try_pg!(w.write_be_f64(self.y)))
+    }
+}
+
+impl RawToSql for LineSegment {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        try!(self.start.raw_to_sql(w));
+        self.end.raw_to_sql(w)
+    }
+}
+
+fn write_points<W: Writer>(points: &[Point], w: &mut W) -> PostgresResult<()> {
+    try_pg!(w.write_be_i32(points.len() as i32));
+    for point in points.iter() {
+        try!(point.raw_to_sql(w));
+    }
+    Ok(())
+}
+
+impl RawToSql for Path {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        try_pg!(w.write_u8(self.closed as u8));
+        write_points(self.points.as_slice(), w)
+    }
+}
+
+impl RawToSql for Polygon {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        write_points(self.points.as_slice(), w)
+    }
+}
+
+impl RawToSql for Bitv {
+    fn raw_to_sql<W: Writer>(&self, w: &mut W) -> PostgresResult<()> {
+        try_pg!(w.write_be_i32(self.len() as i32));
+        Ok(try_pg!(w.write(self.to_bytes().as_slice())))
+    }
+}
+
 macro_rules! to_range_impl(
     ($t:ty) => (
         impl RawToSql for Range<$t> {
@@ -643,6 +1468,7 @@ macro_rules! to_range_impl(
 to_range_impl!(i32)
 to_range_impl!(i64)
 to_range_impl!(Timespec)
+to_range_impl!(Date)
 
 impl RawToSql for Json {
     fn raw_to_sql<W: Writer>(&self, raw: &mut W) -> PostgresResult<()> {
@@ -663,6 +1489,23 @@ macro_rules! to_option_impl(
                 }
             }
         }
+    );
+    ($($oid:pat)|+, $t:ty, $declared:expr) => (
+        impl ToSql for Option<$t> {
+            fn to_sql(&self, ty: &PostgresType)
+                    -> PostgresResult<(Format, Option<Vec<u8>>)> {
+                check_types!($($oid)|+, ty)
+
+                match *self {
+                    None => Ok((Text, None)),
+                    Some(ref val) => val.to_sql(ty)
+                }
+            }
+
+            fn declared_type(&self) -> Option<PostgresType> {
+                Some($declared)
+            }
+        }
     )
 )
 
@@ -696,32 +1539,71 @@ macro_rules! to_raw_to_impl(
         }
 
         to_option_impl!($($oid)|+, $t)
+    );
+    ($($oid:ident)|+, $t:ty, $declared:expr) => (
+        impl ToSql for $t {
+            fn to_sql(&self, ty: &PostgresType)
+                    -> PostgresResult<(Format, Option<Vec<u8>>)> {
+                check_types!($($oid)|+, ty)
+
+                let mut writer = MemWriter::new();
+                try!(self.raw_to_sql(&mut writer));
+                Ok((Binary, Some(writer.unwrap())))
+            }
+
+            fn declared_type(&self) -> Option<PostgresType> {
+                Some($declared)
+            }
+        }
+
+        to_option_impl!($($oid)|+, $t, $declared)
     )
 )
 
 to_raw_to_impl!(PgBool, bool)
 to_raw_to_impl!(PgByteA, Vec<u8>)
-to_raw_to_impl!(PgVarchar | PgText | PgCharN | PgName, String)
-to_raw_to_impl!(PgJson, Json)
+to_raw_to_impl!(PgVarchar | PgText | PgCharN | PgName | PgXml, String)
+to_raw_to_impl!(PgCharN | PgVarchar | PgText, char)
+
+impl ToSql for Json {
+    fn to_sql(&self, ty: &PostgresType) -> PostgresResult<(Format, Option<Vec<u8>>)> {
+        check_types!(PgJson | PgJsonb, ty)
+
+        let mut writer = MemWriter::new();
+        if *ty == PgJsonb {
+            try_pg!(writer.write_u8(JSONB_VERSION));
+        }
+        try!(self.raw_to_sql(&mut writer));
+        Ok((Binary, Some(writer.unwrap())))
+    }
+}
+
+to_option_impl!(PgJson | PgJsonb, Json)
+
 to_raw_to_impl!(PgChar, i8)
 to_raw_to_impl!(PgInt2, i16)
 to_raw_to_impl!(PgInt4, i32)
 to_raw_to_impl!(PgInt8, i64)
+to_raw_to_impl!(PgOid, u32)
 to_raw_to_impl!(PgFloat4, f32)
 to_raw_to_impl!(PgFloat8, f64)
-to_raw_to_impl!(PgInt4Range, Range<i32>)
-to_raw_to_impl!(PgInt8Range, Range<i64>)
+// Ranges declare their type, so the server does not have to infer it when they
+// are passed to PostgresConnection::execute. Timespec ranges can be either
+// TSRANGE or TSTZRANGE, so they are left to the server.
+to_raw_to_impl!(PgInt4Range, Range<i32>, PgInt4Range)
+to_raw_to_impl!(PgInt8Range, Range<i64>, PgInt8Range)
 to_raw_to_impl!(PgTsRange | PgTstzRange, Range<Timespec>)
+to_raw_to_impl!(PgDateRange, Range<Date>, PgDateRange)
 
 impl<'a> ToSql for &'a str {
     fn to_sql(&self, ty: &PostgresType)
             -> PostgresResult<(Format, Option<Vec<u8>>)> {
-        check_types!(PgVarchar | PgText | PgCharN | PgName, ty)
+        check_types!(PgVarchar | PgText | PgCharN | PgName | PgXml, ty)
         Ok((Text, Some(Vec::from_slice(self.as_bytes()))))
     }
 }
 
-to_option_impl_lifetime!(PgVarchar | PgText | PgCharN | PgName, &'a str)
+to_option_impl_lifetime!(PgVarchar | PgText | PgCharN | PgName | PgXml, &'a str)
 
 impl<'a> ToSql for &'a [u8] {
     fn to_sql(&self, ty: &PostgresType)
@@ -734,7 +1616,86 @@ impl<'a> ToSql for &'a [u8] {
 to_option_impl_lifetime!(PgByteA, &'a [u8])
 
 to_raw_to_impl!(PgTimestamp | PgTimestampTZ, Timespec)
+to_raw_to_impl!(PgDate, Date)
+to_raw_to_impl!(PgTime, Time)
+to_raw_to_impl!(PgTimeTz, TimeTz)
+to_raw_to_impl!(PgNumeric, Numeric)
+to_raw_to_impl!(PgInterval, Duration)
 to_raw_to_impl!(PgUuid, Uuid)
+to_raw_to_impl!(PgBit | PgVarbit, Bitv)
+to_raw_to_impl!(PgPoint, Point)
+to_raw_to_impl!(PgLseg, LineSegment)
+to_raw_to_impl!(PgPath, Path)
+to_raw_to_impl!(PgPolygon, Polygon)
+
+// Slices and ArrayBases share the same wire format. A slice is sent as a
+// one-dimensional array with a lower bound of 1 and no NULLs.
+fn encode_array<'a, T: RawToSql, I: Iterator<Option<&'a T>>>(ty: &PostgresType,
+                                                            dim_info: &[DimensionInfo],
+                                                            values: I)
+        -> PostgresResult<(Format, Option<Vec<u8>>)> {
+    let mut buf = MemWriter::new();
+
+    try_pg!(buf.write_be_i32(dim_info.len() as i32));
+    try_pg!(buf.write_be_i32(1));
+    try_pg!(buf.write_be_u32(ty.member_type().to_oid()));
+
+    for info in dim_info.iter() {
+        try_pg!(buf.write_be_i32(info.len as i32));
+        try_pg!(buf.write_be_i32(info.lower_bound as i32));
+    }
+
+    for v in values {
+        match v {
+            Some(val) => {
+                let mut inner_buf = MemWriter::new();
+                try!(val.raw_to_sql(&mut inner_buf));
+                let inner_buf = inner_buf.unwrap();
+                try_pg!(buf.write_be_i32(inner_buf.len() as i32));
+                try_pg!(buf.write(inner_buf.as_slice()));
+            }
+            None => try_pg!(buf.write_be_i32(-1))
+        }
+    }
+
+    Ok((Binary, Some(buf.unwrap())))
+}
+
+/// Converts `values` to a one-dimensional Postgres array of type `ty` with a
+/// lower bound of 1.
+///
+/// This is the counterpart of `array_from_sql`. The element OID written in the
+/// array header is taken from `ty`, which is looked up from the server, so it
+/// is correct for user defined types such as enums. Each element is converted
+/// by its own `ToSql` implementation with the array's member type, and must
+/// use the binary format.
+pub fn array_to_sql<T: ToSql>(ty: &PostgresType, values: &[T])
+        -> PostgresResult<(Format, Option<Vec<u8>>)> {
+    let member = match ty.element_type() {
+        Some(member) => member,
+        None => return Err(PgWrongType(ty.clone()))
+    };
+    let mut buf = MemWriter::new();
+
+    try_pg!(buf.write_be_i32(1));
+    try_pg!(buf.write_be_i32(1));
+    try_pg!(buf.write_be_u32(member.to_oid()));
+    try_pg!(buf.write_be_i32(values.len() as i32));
+    try_pg!(buf.write_be_i32(1));
+
+    for v in values.iter() {
+        match try!(v.to_sql(&member)) {
+            (Binary, Some(value)) => {
+                try_pg!(buf.write_be_i32(value.len() as i32));
+                try_pg!(buf.write(value.as_slice()));
+            }
+            (Binary, None) => try_pg!(buf.write_be_i32(-1)),
+            (Text, _) => return Err(PgWrongType(member))
+        }
+    }
+
+    Ok((Binary, Some(buf.unwrap())))
+}
 
 macro_rules! to_array_impl(
     ($($oid:ident)|+, $t:ty) => (
@@ -742,35 +1703,22 @@ macro_rules! to_array_impl(
             fn to_sql(&self, ty: &PostgresType)
                     -> PostgresResult<(Format, Option<Vec<u8>>)> {
                 check_types!($($oid)|+, ty)
-                let mut buf = MemWriter::new();
-
-                try_pg!(buf.write_be_i32(self.dimension_info().len() as i32));
-                try_pg!(buf.write_be_i32(1));
-                try_pg!(buf.write_be_u32(ty.member_type().to_oid()));
-
-                for info in self.dimension_info().iter() {
-                    try_pg!(buf.write_be_i32(info.len as i32));
-                    try_pg!(buf.write_be_i32(info.lower_bound as i32));
-                }
+                encode_array(ty, self.dimension_info(), self.values().map(|v| v.as_ref()))
+            }
+        }
 
-                for v in self.values() {
-                    match *v {
-                        Some(ref val) => {
-                            let mut inner_buf = MemWriter::new();
-                            try!(val.raw_to_sql(&mut inner_buf));
-                            let inner_buf = inner_buf.unwrap();
-                            try_pg!(buf.write_be_i32(inner_buf.len() as i32));
-                            try_pg!(buf.write(inner_buf.as_slice()));
-                        }
-                        None => try_pg!(buf.write_be_i32(-1))
-                    }
-                }
+        to_option_impl!($($oid)|+, ArrayBase<Option<$t>>)
 
-                Ok((Binary, Some(buf.unwrap())))
+        impl<'a> ToSql for &'a [$t] {
+            fn to_sql(&self, ty: &PostgresType)
+                    -> PostgresResult<(Format, Option<Vec<u8>>)> {
+                check_types!($($oid)|+, ty)
+                let dim_info = [DimensionInfo { len: self.len(), lower_bound: 1 }];
+                encode_array(ty, dim_info, self.iter().map(|v| Some(v)))
             }
         }
 
-        to_option_impl!($($oid)|+, ArrayBase<Option<$t>>)
+        to_option_impl_lifetime!($($oid)|+, &'a [$t])
     )
 )
 
@@ -782,14 +1730,58 @@ to_array_impl!(PgInt4Array, i32)
 to_array_impl!(PgInt8Array, i64)
 to_array_impl!(PgTextArray | PgCharNArray | PgVarcharArray | PgNameArray, String)
 to_array_impl!(PgTimestampArray | PgTimestampTZArray, Timespec)
+to_array_impl!(PgDateArray, Date)
+to_array_impl!(PgIntervalArray, Duration)
 to_array_impl!(PgFloat4Array, f32)
 to_array_impl!(PgFloat8Array, f64)
 to_array_impl!(PgUuidArray, Uuid)
+to_array_impl!(PgBitArray | PgVarbitArray, Bitv)
 to_array_impl!(PgInt4RangeArray, Range<i32>)
 to_array_impl!(PgTsRangeArray | PgTstzRangeArray, Range<Timespec>)
+to_array_impl!(PgDateRangeArray, Range<Date>)
 to_array_impl!(PgInt8RangeArray, Range<i64>)
 to_array_impl!(PgJsonArray, Json)
 
+impl<T: PartialOrd+Normalizable+fmt::Show> ToSql for TextRange<T> {
+    fn to_sql(&self, _: &PostgresType)
+            -> PostgresResult<(Format, Option<Vec<u8>>)> {
+        let TextRange(ref range) = *self;
+        if range.is_empty() {
+            return Ok((Text, Some(Vec::from_slice(b"empty"))));
+        }
+
+        let mut buf = String::new();
+        match range.lower() {
+            Some(bound) => {
+                buf.push_char(match bound.type_ { Inclusive => '[', Exclusive => '(' });
+                push_quoted_range_value(&mut buf, &bound.value);
+            }
+            None => buf.push_char('('),
+        }
+        buf.push_char(',');
+        match range.upper() {
+            Some(bound) => {
+                push_quoted_range_value(&mut buf, &bound.value);
+                buf.push_char(match bound.type_ { Inclusive => ']', Exclusive => ')' });
+            }
+            None => buf.push_char(')'),
+        }
+
+        Ok((Text, Some(buf.into_bytes())))
+    }
+}
+
+fn push_quoted_range_value<T: fmt::Show>(buf: &mut String, value: &T) {
+    buf.push_char('"');
+    for c in format!("{}", value).as_slice().chars() {
+        if c == '"' || c == '\\' {
+            buf.push_char('\\');
+        }
+        buf.push_char(c);
+    }
+    buf.push_char('"');
+}
+
 impl ToSql for HashMap<String, Option<String>> {
     fn to_sql(&self, ty: &PostgresType)
             -> PostgresResult<(Format, Option<Vec<u8>>)> {
@@ -0,0 +1,58 @@
+//! An arbitrary precision decimal type
+
+use std::fmt;
+use std::from_str::FromStr;
+
+/// A Postgres `NUMERIC` value.
+///
+/// Finite values are stored as decimal strings such as `-12.340`, exactly as
+/// Postgres would print them. The number of digits after the decimal point
+/// is significant, so `1.5` and `1.50` are not equal.
+#[deriving(PartialEq, Eq, Clone)]
+pub enum Numeric {
+    /// A finite value
+    NumericValue(String),
+    /// Not-a-number
+    NumericNaN,
+    /// Positive infinity
+    NumericInfinity,
+    /// Negative infinity
+    NumericNegInfinity,
+}
+
+impl FromStr for Numeric {
+    /// Parses a value in the format Postgres prints `NUMERIC` values in:
+    /// an optional minus sign, one or more digits and optionally a decimal
+    /// point followed by one or more digits. `NaN`, `Infinity` and
+    /// `-Infinity` are also accepted.
+    fn from_str(s: &str) -> Option<Numeric> {
+        match s {
+            "NaN" => return Some(NumericNaN),
+            "Infinity" => return Some(NumericInfinity),
+            "-Infinity" => return Some(NumericNegInfinity),
+            _ => {}
+        }
+
+        let unsigned = if s.starts_with("-") { s.slice_from(1) } else { s };
+        let (int, frac) = match unsigned.find('.') {
+            Some(idx) => (unsigned.slice_to(idx), Some(unsigned.slice_from(idx + 1))),
+            None => (unsigned, None),
+        };
+        let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_digit());
+        if !is_digits(int) || !frac.map_or(true, |frac| is_digits(frac)) {
+            return None;
+        }
+        Some(NumericValue(s.to_str()))
+    }
+}
+
+impl fmt::Show for Numeric {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NumericValue(ref s) => write!(fmt, "{}", s),
+            NumericNaN => write!(fmt, "NaN"),
+            NumericInfinity => write!(fmt, "Infinity"),
+            NumericNegInfinity => write!(fmt, "-Infinity"),
+        }
+    }
+}
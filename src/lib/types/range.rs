@@ -1,11 +1,17 @@
 //! Types dealing with ranges of values
 #![macro_escape]
 
+use std::cmp;
 use std::fmt;
+use std::io::{BufReader, IoError, IoResult, InvalidInput, MemWriter};
 use std::i32;
 use std::i64;
+use std::mem;
+use serialize::{Encodable, Encoder, Decodable, Decoder};
 use time::Timespec;
 
+use types::date::Date;
+
 /// The `quote!` macro can make it easier to create ranges. It roughly mirrors
 /// traditional mathematic range syntax.
 ///
@@ -45,6 +51,10 @@ use time::Timespec;
 ///     r = range!('(', ')');
 ///     // an empty interval
 ///     r = range!(empty);
+///     // shorthand for range!('[' 5i32, 10i32 ')')
+///     r = range!(5i32 => 10i32);
+///     // shorthand for range!('[' 5i32, 10i32 ']')
+///     r = range!(5i32 ..= 10i32);
 /// }
 #[macro_export]
 macro_rules! range(
@@ -77,7 +87,9 @@ macro_rules! range(
     ('[' $l:expr, $h:expr ']') => (
         Range::new(Some(RangeBound::new($l, Inclusive)),
                    Some(RangeBound::new($h, Inclusive)))
-    )
+    );
+    ($l:expr => $h:expr) => (range!('[' $l, $h ')'));
+    ($l:expr ..= $h:expr) => (range!('[' $l, $h ']'))
 )
 
 /// A trait that normalizes a range bound for a type
@@ -92,6 +104,31 @@ pub trait Normalizable {
     /// Postgres type.
     fn normalize<S: BoundSided>(bound: RangeBound<S, Self>)
             -> RangeBound<S, Self>;
+
+    /// Determines if `normalize` can be applied to a bound without failing.
+    ///
+    /// The default implementation always returns `true`.
+    fn can_normalize<S: BoundSided>(_bound: &RangeBound<S, Self>) -> bool {
+        true
+    }
+
+    /// Returns the value nearest to a bound which the bound includes, if
+    /// there is one.
+    ///
+    /// For an inclusive bound this is the value of the bound. For an exclusive
+    /// bound of a discrete type such as i32 it is the adjacent value on the
+    /// included side, while an exclusive bound of a continuous type such as
+    /// Timespec has no nearest included value.
+    ///
+    /// The default implementation treats the type as continuous, so discrete
+    /// types should override it.
+    fn nearest_included<S: BoundSided>(bound: RangeBound<S, Self>)
+            -> Option<Self> {
+        match bound.type_ {
+            Inclusive => Some(bound.value),
+            Exclusive => None,
+        }
+    }
 }
 
 macro_rules! bounded_normalizable(
@@ -111,6 +148,22 @@ macro_rules! bounded_normalizable(
                     _ => bound
                 }
             }
+
+            fn can_normalize<S: BoundSided>(bound: &RangeBound<S, $t>) -> bool {
+                match (BoundSided::side(None::<S>), bound.type_) {
+                    (Upper, Inclusive) | (Lower, Exclusive) => bound.value != $t::MAX,
+                    _ => true
+                }
+            }
+
+            fn nearest_included<S: BoundSided>(bound: RangeBound<S, $t>)
+                    -> Option<$t> {
+                match (BoundSided::side(None::<S>), bound.type_) {
+                    (_, Inclusive) => Some(bound.value),
+                    (Upper, Exclusive) => bound.value.checked_sub(&1),
+                    (Lower, Exclusive) => bound.value.checked_add(&1),
+                }
+            }
         }
     )
 )
@@ -118,6 +171,29 @@ macro_rules! bounded_normalizable(
 bounded_normalizable!(i32)
 bounded_normalizable!(i64)
 
+// NaN compares false to everything, so a range with a NaN bound would have no
+// meaningful ordering. Postgres rejects them as well.
+macro_rules! float_normalizable(
+    ($t:ident) => (
+        impl Normalizable for $t {
+            fn normalize<S: BoundSided>(bound: RangeBound<S, $t>)
+                    -> RangeBound<S, $t> {
+                if bound.value.is_nan() {
+                    fail!("NaN is not a valid range bound");
+                }
+                bound
+            }
+
+            fn can_normalize<S: BoundSided>(bound: &RangeBound<S, $t>) -> bool {
+                !bound.value.is_nan()
+            }
+        }
+    )
+)
+
+float_normalizable!(f32)
+float_normalizable!(f64)
+
 impl Normalizable for Timespec {
     fn normalize<S: BoundSided>(bound: RangeBound<S, Timespec>)
             -> RangeBound<S, Timespec> {
@@ -125,6 +201,37 @@ impl Normalizable for Timespec {
     }
 }
 
+impl Normalizable for Date {
+    fn normalize<S: BoundSided>(bound: RangeBound<S, Date>)
+            -> RangeBound<S, Date> {
+        match (BoundSided::side(None::<S>), bound.type_) {
+            (Upper, Inclusive) | (Lower, Exclusive) => {
+                assert!(bound.value.days() != i32::MAX);
+                let type_ = flip(bound.type_);
+                RangeBound::new(Date::from_days(bound.value.days() + 1), type_)
+            }
+            _ => bound
+        }
+    }
+
+    fn can_normalize<S: BoundSided>(bound: &RangeBound<S, Date>) -> bool {
+        match (BoundSided::side(None::<S>), bound.type_) {
+            (Upper, Inclusive) | (Lower, Exclusive) => bound.value.days() != i32::MAX,
+            _ => true
+        }
+    }
+
+    fn nearest_included<S: BoundSided>(bound: RangeBound<S, Date>)
+            -> Option<Date> {
+        let days = bound.value.days();
+        match (BoundSided::side(None::<S>), bound.type_) {
+            (_, Inclusive) => Some(bound.value),
+            (Upper, Exclusive) => days.checked_sub(&1).map(Date::from_days),
+            (Lower, Exclusive) => days.checked_add(&1).map(Date::from_days),
+        }
+    }
+}
+
 #[deriving(PartialEq, Eq)]
 enum BoundSide {
     Upper,
@@ -156,7 +263,7 @@ impl BoundSided for LowerBound {
 }
 
 /// The type of a range bound
-#[deriving(PartialEq, Eq, Clone)]
+#[deriving(PartialEq, Eq, Clone, Encodable, Decodable)]
 pub enum BoundType {
     /// The bound includes its value
     Inclusive,
@@ -164,6 +271,21 @@ pub enum BoundType {
     Exclusive
 }
 
+/// The relationship between two ranges, as reported by `Range::intersect_ext`
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum IntersectKind {
+    /// The ranges have no values in common
+    Disjoint,
+    /// The ranges are equal
+    Same,
+    /// The first range contains the second
+    SelfContainsOther,
+    /// The second range contains the first
+    OtherContainsSelf,
+    /// The ranges overlap, but neither contains the other
+    Partial
+}
+
 /// Represents a one-sided bound.
 ///
 /// The side is determined by the `S` phantom parameter.
@@ -213,11 +335,11 @@ impl<S: BoundSided, T: PartialOrd> PartialOrd for RangeBound<S, T> {
     fn partial_cmp(&self, other: &RangeBound<S, T>) -> Option<Ordering> {
         match (BoundSided::side(None::<S>), self.type_, other.type_,
                 self.value.partial_cmp(&other.value)) {
-            (Upper, Exclusive, Inclusive, Some(Equal))
-            | (Lower, Inclusive, Exclusive, Some(Equal)) => Some(Less),
-            (Upper, Inclusive, Exclusive, Some(Equal))
-            | (Lower, Exclusive, Inclusive, Some(Equal)) => Some(Greater),
-            (_, _, _, cmp) => cmp,
+            (Upper, Exclusive, Inclusive, Some(cmp::Equal))
+            | (Lower, Inclusive, Exclusive, Some(cmp::Equal)) => Some(cmp::Less),
+            (Upper, Inclusive, Exclusive, Some(cmp::Equal))
+            | (Lower, Exclusive, Inclusive, Some(cmp::Equal)) => Some(cmp::Greater),
+            (_, _, _, ord) => ord,
         }
     }
 }
@@ -226,15 +348,40 @@ impl<S: BoundSided, T: Ord> Ord for RangeBound<S, T> {
     fn cmp(&self, other: &RangeBound<S, T>) -> Ordering {
         match (BoundSided::side(None::<S>), self.type_, other.type_,
                 self.value.cmp(&other.value)) {
-            (Upper, Exclusive, Inclusive, Equal)
-            | (Lower, Inclusive, Exclusive, Equal) => Less,
-            (Upper, Inclusive, Exclusive, Equal)
-            | (Lower, Exclusive, Inclusive, Equal) => Greater,
+            (Upper, Exclusive, Inclusive, cmp::Equal)
+            | (Lower, Inclusive, Exclusive, cmp::Equal) => cmp::Less,
+            (Upper, Inclusive, Exclusive, cmp::Equal)
+            | (Lower, Exclusive, Inclusive, cmp::Equal) => cmp::Greater,
             (_, _, _, ord) => ord,
         }
     }
 }
 
+fn bound_type(inclusive: bool) -> BoundType {
+    if inclusive { Inclusive } else { Exclusive }
+}
+
+/// A bound is encoded as a struct with a `value` field and an `inclusive`
+/// flag, e.g. `{"value":10,"inclusive":true}` in JSON.
+impl<E, S: Encoder<E>, B: BoundSided, T: Encodable<S, E>> Encodable<S, E> for RangeBound<B, T> {
+    fn encode(&self, s: &mut S) -> Result<(), E> {
+        s.emit_struct("RangeBound", 2, |s| {
+            try!(s.emit_struct_field("value", 0, |s| self.value.encode(s)));
+            s.emit_struct_field("inclusive", 1, |s| (self.type_ == Inclusive).encode(s))
+        })
+    }
+}
+
+impl<E, D: Decoder<E>, B: BoundSided, T: Decodable<D, E>> Decodable<D, E> for RangeBound<B, T> {
+    fn decode(d: &mut D) -> Result<RangeBound<B, T>, E> {
+        d.read_struct("RangeBound", 2, |d| {
+            let value = try!(d.read_struct_field("value", 0, |d| Decodable::decode(d)));
+            let inclusive = try!(d.read_struct_field("inclusive", 1, |d| Decodable::decode(d)));
+            Ok(RangeBound { value: value, type_: bound_type(inclusive) })
+        })
+    }
+}
+
 impl<S: BoundSided, T: PartialOrd> RangeBound<S, T> {
     /// Constructs a new range bound
     pub fn new(value: T, type_: BoundType) -> RangeBound<S, T> {
@@ -269,17 +416,57 @@ impl<'a, S: BoundSided, T: PartialEq> PartialEq for OptBound<'a, S, T> {
 impl<'a, S: BoundSided, T: PartialOrd> PartialOrd for OptBound<'a, S, T> {
     fn partial_cmp(&self, other: &OptBound<'a, S, T>) -> Option<Ordering> {
         match (*self, *other, BoundSided::side(None::<S>)) {
-            (OptBound(None), OptBound(None), _) => Some(Equal),
+            (OptBound(None), OptBound(None), _) => Some(cmp::Equal),
             (OptBound(None), _, Lower)
-            | (_, OptBound(None), Upper) => Some(Less),
+            | (_, OptBound(None), Upper) => Some(cmp::Less),
             (OptBound(None), _, Upper)
-            | (_, OptBound(None), Lower) => Some(Greater),
+            | (_, OptBound(None), Lower) => Some(cmp::Greater),
             (OptBound(Some(a)), OptBound(Some(b)), _) => a.partial_cmp(b)
         }
     }
 }
 
+impl<'a, S: BoundSided, T: Eq> Eq for OptBound<'a, S, T> {}
+
+impl<'a, S: BoundSided, T: Ord> Ord for OptBound<'a, S, T> {
+    fn cmp(&self, other: &OptBound<'a, S, T>) -> Ordering {
+        match (*self, *other, BoundSided::side(None::<S>)) {
+            (OptBound(None), OptBound(None), _) => cmp::Equal,
+            (OptBound(None), _, Lower)
+            | (_, OptBound(None), Upper) => cmp::Less,
+            (OptBound(None), _, Upper)
+            | (_, OptBound(None), Lower) => cmp::Greater,
+            (OptBound(Some(a)), OptBound(Some(b)), _) => a.cmp(b)
+        }
+    }
+}
+
 /// Represents a range of values.
+///
+/// Ranges are always stored in normalized form, so two ranges of a discrete
+/// type which contain the same values compare equal however they were
+/// written. For example, `[1,5]` and `[1,6)` are both stored as `[1,6)`.
+///
+/// `Range::between(a, b)` contains the same values as the iterator
+/// `range(a, b)`, and `Range::between_inclusive(a, b)` the same values as
+/// `range_inclusive(a, b)`. Half-bounded ranges are created with `at_least`,
+/// `up_to` and `up_to_inclusive`.
+///
+/// # Empty ranges
+///
+/// The empty range contains no values, and follows the same rules as the
+/// Postgres empty range in every operation:
+///
+/// * It has no bounds, and `lower`, `upper` and their accessors return `None`.
+/// * It contains no value, and is contained by every range, including
+///   another empty range. A non-empty range never lies within it.
+/// * Its intersection with any range is empty, and its union with a range is
+///   that other range.
+/// * Removing it from a range with `complement_within` leaves the range
+///   unchanged, while nothing remains of an empty universe.
+/// * It sorts before every other range, and all empty ranges are equal.
+/// * `map` leaves it empty, `clamp` has nothing to clamp to, and
+///   `extend_to_include` turns it into the range containing only the value.
 #[deriving(PartialEq, Eq, Clone)]
 pub struct Range<T> {
     inner: InnerRange<T>,
@@ -292,29 +479,179 @@ enum InnerRange<T> {
            Option<RangeBound<UpperBound, T>>)
 }
 
-impl<T: fmt::Show> fmt::Show for Range<T> {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match self.inner {
-            Empty => write!(fmt, "empty"),
-            Normal(ref lower, ref upper) => {
-                match *lower {
-                    Some(ref bound) => try!(write!(fmt, "{}", bound)),
-                    None => try!(write!(fmt, "(")),
+/// Ranges are ordered the same way Postgres orders them.
+///
+/// The empty range sorts before every other range. Non-empty ranges are
+/// ordered by their lower bounds and then by their upper bounds. A missing
+/// lower bound sorts before every other lower bound, and a missing upper
+/// bound sorts after every other upper bound. An inclusive lower bound sorts
+/// before an exclusive lower bound with the same value, while an exclusive
+/// upper bound sorts before an inclusive upper bound with the same value.
+///
+/// Bounds are normalized when a range is created, so for discrete types
+/// `[1,5]` and `[1,6)` are the same range and compare equal.
+impl<T: PartialOrd> PartialOrd for Range<T> {
+    fn partial_cmp(&self, other: &Range<T>) -> Option<Ordering> {
+        match (&self.inner, &other.inner) {
+            (&Empty, &Empty) => Some(cmp::Equal),
+            (&Empty, _) => Some(cmp::Less),
+            (_, &Empty) => Some(cmp::Greater),
+            (&Normal(ref l1, ref u1), &Normal(ref l2, ref u2)) => {
+                match OptBound(l1.as_ref()).partial_cmp(&OptBound(l2.as_ref())) {
+                    Some(cmp::Equal) =>
+                        OptBound(u1.as_ref()).partial_cmp(&OptBound(u2.as_ref())),
+                    ord => ord,
                 }
-                try!(write!(fmt, ","));
-                match *upper {
-                    Some(ref bound) => write!(fmt, "{}", bound),
-                    None => write!(fmt, ")"),
+            }
+        }
+    }
+}
+
+impl<T: Ord> Ord for Range<T> {
+    fn cmp(&self, other: &Range<T>) -> Ordering {
+        match (&self.inner, &other.inner) {
+            (&Empty, &Empty) => cmp::Equal,
+            (&Empty, _) => cmp::Less,
+            (_, &Empty) => cmp::Greater,
+            (&Normal(ref l1, ref u1), &Normal(ref l2, ref u2)) => {
+                match OptBound(l1.as_ref()).cmp(&OptBound(l2.as_ref())) {
+                    cmp::Equal => OptBound(u1.as_ref()).cmp(&OptBound(u2.as_ref())),
+                    ord => ord,
                 }
             }
         }
     }
 }
 
+/// Formats the range in the Postgres text representation, e.g. `[1,10)`,
+/// `(,10]` or `empty`.
+///
+/// The output matches the server's own `range::text` output as long as the
+/// `Show` implementation of `T` matches the text representation of the
+/// element type, as is the case for the integer types.
+impl<T: fmt::Show> fmt::Show for Range<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.display_with(|value| value.to_str()))
+    }
+}
+
+impl<T> Range<T> {
+    /// Renders the range in the same form as its `Show` implementation, but
+    /// with each bound's value formatted by `f`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # #![feature(phase)]
+    /// # #[phase(plugin, link)] extern crate postgres;
+    /// # use postgres::types::range::{Range, RangeBound, Inclusive, Exclusive};
+    /// # fn main() {
+    /// let r = range!('[' 10i32, 255i32 ')');
+    /// assert_eq!("[A,FF)", r.display_with(|v| format!("{:X}", *v)).as_slice());
+    /// # }
+    /// ```
+    pub fn display_with(&self, f: |&T| -> String) -> String {
+        let (lower, upper) = match self.inner {
+            Empty => return "empty".to_str(),
+            Normal(ref lower, ref upper) => (lower, upper),
+        };
+
+        let mut out = String::new();
+        match *lower {
+            Some(ref bound) => {
+                out.push_char(match bound.type_ {
+                    Inclusive => '[',
+                    Exclusive => '(',
+                });
+                out.push_str(f(&bound.value).as_slice());
+            }
+            None => out.push_char('('),
+        }
+        out.push_char(',');
+        match *upper {
+            Some(ref bound) => {
+                out.push_str(f(&bound.value).as_slice());
+                out.push_char(match bound.type_ {
+                    Inclusive => ']',
+                    Exclusive => ')',
+                });
+            }
+            None => out.push_char(')'),
+        }
+        out
+    }
+}
+
+/// A range is encoded as a struct with `empty`, `lower`, `upper`, `lower_inc`
+/// and `upper_inc` fields. A missing bound is encoded as a null value with an
+/// inclusivity flag of `false`, and an empty range has `empty` set and both
+/// bounds missing. In JSON, `[1,10)` is encoded as
+/// `{"empty":false,"lower":1,"upper":10,"lower_inc":true,"upper_inc":false}`.
+///
+/// This is unrelated to the compact binary format produced by `Range::to_bytes`.
+impl<E, S: Encoder<E>, T: Encodable<S, E>> Encodable<S, E> for Range<T> {
+    fn encode(&self, s: &mut S) -> Result<(), E> {
+        let (empty, lower, upper) = match self.inner {
+            Empty => (true, None, None),
+            Normal(ref lower, ref upper) => (false, lower.as_ref(), upper.as_ref()),
+        };
+        let lower_inc = lower.map_or(false, |b| b.type_ == Inclusive);
+        let upper_inc = upper.map_or(false, |b| b.type_ == Inclusive);
+
+        s.emit_struct("Range", 5, |s| {
+            try!(s.emit_struct_field("empty", 0, |s| empty.encode(s)));
+            try!(s.emit_struct_field("lower", 1, |s| lower.map(|b| &b.value).encode(s)));
+            try!(s.emit_struct_field("upper", 2, |s| upper.map(|b| &b.value).encode(s)));
+            try!(s.emit_struct_field("lower_inc", 3, |s| lower_inc.encode(s)));
+            s.emit_struct_field("upper_inc", 4, |s| upper_inc.encode(s))
+        })
+    }
+}
+
+/// The decoded bounds are passed to `Range::new`, so they are normalized and
+/// a range with no values decodes as the empty range. The bounds of a range
+/// with `empty` set are ignored. A bound which cannot be normalized, such as
+/// an inclusive `i32::MAX` upper bound, results in a decoding error.
+impl<E, D: Decoder<E>, T: PartialOrd+Normalizable+Decodable<D, E>> Decodable<D, E> for Range<T> {
+    fn decode(d: &mut D) -> Result<Range<T>, E> {
+        d.read_struct("Range", 5, |d| {
+            let empty: bool = try!(d.read_struct_field("empty", 0, |d| Decodable::decode(d)));
+            let lower: Option<T> = try!(d.read_struct_field("lower", 1, |d| Decodable::decode(d)));
+            let upper: Option<T> = try!(d.read_struct_field("upper", 2, |d| Decodable::decode(d)));
+            let lower_inc = try!(d.read_struct_field("lower_inc", 3, |d| Decodable::decode(d)));
+            let upper_inc = try!(d.read_struct_field("upper_inc", 4, |d| Decodable::decode(d)));
+
+            if empty {
+                return Ok(Range::empty());
+            }
+
+            let lower = lower.map(|value| RangeBound::new(value, bound_type(lower_inc)));
+            if !lower.as_ref().map_or(true, |b| Normalizable::can_normalize(b)) {
+                return Err(d.error("invalid lower bound"));
+            }
+            let upper = upper.map(|value| RangeBound::new(value, bound_type(upper_inc)));
+            if !upper.as_ref().map_or(true, |b| Normalizable::can_normalize(b)) {
+                return Err(d.error("invalid upper bound"));
+            }
+            Ok(Range::new(lower, upper))
+        })
+    }
+}
+
 impl<T: PartialOrd+Normalizable> Range<T> {
     /// Creates a new range.
     ///
     /// If a bound is `None`, the range is unbounded in that direction.
+    ///
+    /// The bounds are normalized before the range is checked for emptiness,
+    /// as Postgres does. For a discrete type like `i32`, `[5,5]` is therefore
+    /// the single element range `[5,6)`, while `[5,5)` and `(5,5]` are empty.
+    /// For a continuous type like `Timespec`, `[t,t]` contains only `t` and
+    /// `[t,t)` is empty.
+    ///
+    /// # Failure
+    ///
+    /// Fails if a bound of an `f32` or `f64` range is NaN.
     pub fn new(lower: Option<RangeBound<LowerBound, T>>,
                upper: Option<RangeBound<UpperBound, T>>) -> Range<T> {
         let lower = lower.map(|bound| Normalizable::normalize(bound));
@@ -341,6 +678,42 @@ impl<T: PartialOrd+Normalizable> Range<T> {
         Range { inner: Empty }
     }
 
+    /// Creates the range `[lower,)`, containing every value greater than or
+    /// equal to `lower`.
+    pub fn at_least(lower: T) -> Range<T> {
+        Range::new(Some(RangeBound::new(lower, Inclusive)), None)
+    }
+
+    /// Creates the range `(,upper)`, containing every value less than
+    /// `upper`.
+    pub fn up_to(upper: T) -> Range<T> {
+        Range::new(None, Some(RangeBound::new(upper, Exclusive)))
+    }
+
+    /// Creates the range `(,upper]`, containing every value less than or
+    /// equal to `upper`.
+    pub fn up_to_inclusive(upper: T) -> Range<T> {
+        Range::new(None, Some(RangeBound::new(upper, Inclusive)))
+    }
+
+    /// Creates the range `[lower,upper)`, containing every value greater than
+    /// or equal to `lower` and less than `upper`.
+    ///
+    /// The range is empty if `upper` is not greater than `lower`.
+    pub fn between(lower: T, upper: T) -> Range<T> {
+        Range::new(Some(RangeBound::new(lower, Inclusive)),
+                   Some(RangeBound::new(upper, Exclusive)))
+    }
+
+    /// Creates the range `[lower,upper]`, containing every value greater than
+    /// or equal to `lower` and less than or equal to `upper`.
+    ///
+    /// The range is empty if `upper` is less than `lower`.
+    pub fn between_inclusive(lower: T, upper: T) -> Range<T> {
+        Range::new(Some(RangeBound::new(lower, Inclusive)),
+                   Some(RangeBound::new(upper, Inclusive)))
+    }
+
     /// Determines if this range is the empty range.
     pub fn is_empty(&self) -> bool {
         match self.inner {
@@ -349,6 +722,15 @@ impl<T: PartialOrd+Normalizable> Range<T> {
         }
     }
 
+    /// Determines if this range is unbounded in both directions, and so
+    /// contains every value.
+    pub fn is_full(&self) -> bool {
+        match self.inner {
+            Normal(None, None) => true,
+            _ => false
+        }
+    }
+
     /// Returns the lower bound if it exists.
     pub fn lower<'a>(&'a self) -> Option<&'a RangeBound<LowerBound, T>> {
         match self.inner {
@@ -365,6 +747,34 @@ impl<T: PartialOrd+Normalizable> Range<T> {
         }
     }
 
+    /// Returns the type of the lower bound if it exists.
+    pub fn lower_type(&self) -> Option<BoundType> {
+        self.lower().map(|b| b.type_)
+    }
+
+    /// Returns the type of the upper bound if it exists.
+    pub fn upper_type(&self) -> Option<BoundType> {
+        self.upper().map(|b| b.type_)
+    }
+
+    /// Converts the range into a range of another type by applying `f` to
+    /// the value of each bound.
+    ///
+    /// The type of each bound is preserved, an empty range stays empty, and
+    /// the result is normalized again. `f` should be monotonic: if it does
+    /// not preserve the order of values, the resulting range will not contain
+    /// the values corresponding to those in the original range.
+    pub fn map<U: PartialOrd+Normalizable>(self, f: |T| -> U) -> Range<U> {
+        match self.inner {
+            Empty => Range::empty(),
+            Normal(lower, upper) => {
+                let lower = lower.map(|b| RangeBound::new(f(b.value), b.type_));
+                let upper = upper.map(|b| RangeBound::new(f(b.value), b.type_));
+                Range::new(lower, upper)
+            }
+        }
+    }
+
     /// Determines if a value lies within this range.
     pub fn contains(&self, value: &T) -> bool {
         match self.inner {
@@ -389,8 +799,29 @@ impl<T: PartialOrd+Normalizable> Range<T> {
         OptBound(self.lower()) <= OptBound(other.lower()) &&
             OptBound(self.upper()) >= OptBound(other.upper())
     }
+
+    /// Determines if this range lies completely within another range.
+    ///
+    /// This is the inverse of `contains_range`, and corresponds to Postgres's
+    /// `<@` operator. An empty range is contained by every range.
+    pub fn is_contained_by(&self, other: &Range<T>) -> bool {
+        other.contains_range(self)
+    }
 }
 
+/// A wrapper which sends a range to the server in the text format.
+///
+/// The `ToSql` implementation of `Range` uses the binary format, which only
+/// works for the range types known to this library. The server parses the
+/// text format of any range type, so this allows values to be sent to other
+/// range types, such as user-defined ones, as long as the `Show`
+/// implementation of `T` produces a valid text representation of the
+/// element type.
+///
+/// Empty and unbounded ranges are sent as `empty` and `(,)`, and bound values
+/// are quoted.
+pub struct TextRange<T>(pub Range<T>);
+
 fn order<T:PartialOrd>(a: T, b: T) -> (T, T) {
     if a < b {
         (a, b)
@@ -399,7 +830,186 @@ fn order<T:PartialOrd>(a: T, b: T) -> (T, T) {
     }
 }
 
+fn order_owned<S: BoundSided, T: PartialOrd>(a: Option<RangeBound<S, T>>,
+                                             b: Option<RangeBound<S, T>>)
+        -> (Option<RangeBound<S, T>>, Option<RangeBound<S, T>>) {
+    let a_first = OptBound(a.as_ref()) < OptBound(b.as_ref());
+    if a_first {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn discontiguous<T: PartialOrd>(u_lower: Option<&RangeBound<LowerBound, T>>,
+                                l_upper: Option<&RangeBound<UpperBound, T>>)
+        -> bool {
+    match (u_lower, l_upper) {
+        (Some(&RangeBound { value: ref l, type_: Exclusive }),
+         Some(&RangeBound { value: ref u, type_: Exclusive })) => l >= u,
+        (Some(&RangeBound { value: ref l, .. }),
+         Some(&RangeBound { value: ref u, .. })) => l > u,
+        _ => false
+    }
+}
+
+fn flip(type_: BoundType) -> BoundType {
+    match type_ {
+        Inclusive => Exclusive,
+        Exclusive => Inclusive,
+    }
+}
+
+impl<T: PartialOrd+Normalizable> Range<T> {
+    /// Like `intersect`, but consumes both ranges.
+    ///
+    /// The bounds of the result are moved out of the inputs rather than
+    /// cloned, so `T` does not need to implement `Clone`.
+    pub fn into_intersect(self, other: Range<T>) -> Range<T> {
+        let (self_lower, self_upper) = match self.inner {
+            Empty => return Range::empty(),
+            Normal(lower, upper) => (lower, upper),
+        };
+        let (other_lower, other_upper) = match other.inner {
+            Empty => return Range::empty(),
+            Normal(lower, upper) => (lower, upper),
+        };
+
+        let (_, lower) = order_owned(self_lower, other_lower);
+        let (upper, _) = order_owned(self_upper, other_upper);
+
+        Range::new(lower, upper)
+    }
+
+    /// Like `union`, but consumes both ranges.
+    ///
+    /// The bounds of the result are moved out of the inputs rather than
+    /// cloned, so `T` does not need to implement `Clone`.
+    pub fn into_union(self, other: Range<T>) -> Option<Range<T>> {
+        let (self_lower, self_upper) = match self.inner {
+            Empty => return Some(other),
+            Normal(lower, upper) => (lower, upper),
+        };
+        let (other_lower, other_upper) = match other.inner {
+            Empty => return Some(Range { inner: Normal(self_lower, self_upper) }),
+            Normal(lower, upper) => (lower, upper),
+        };
+
+        let (l_lower, u_lower) = order_owned(self_lower, other_lower);
+        let (l_upper, u_upper) = order_owned(self_upper, other_upper);
+
+        if discontiguous(u_lower.as_ref(), l_upper.as_ref()) {
+            None
+        } else {
+            Some(Range::new(l_lower, u_upper))
+        }
+    }
+}
+
 impl<T: PartialOrd+Normalizable+Clone> Range<T> {
+    /// Creates a new range containing only `value`.
+    pub fn singleton(value: T) -> Range<T> {
+        Range::new(Some(RangeBound::new(value.clone(), Inclusive)),
+                   Some(RangeBound::new(value, Inclusive)))
+    }
+
+    /// Returns a copy of the value of the lower bound if it exists.
+    pub fn lower_value(&self) -> Option<T> {
+        self.lower().map(|b| b.value.clone())
+    }
+
+    /// Returns a copy of the value of the upper bound if it exists.
+    pub fn upper_value(&self) -> Option<T> {
+        self.upper().map(|b| b.value.clone())
+    }
+
+    /// Returns the single value contained by this range, if it contains
+    /// exactly one value.
+    ///
+    /// For a discrete type like `i32` this recognizes the normalized form
+    /// `[n,n+1)`, and for a continuous type like `Timespec` it recognizes
+    /// `[t,t]`.
+    pub fn as_singleton<'a>(&'a self) -> Option<&'a T> {
+        let (lower, upper) = match (self.lower(), self.upper()) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            _ => return None,
+        };
+
+        if lower.type_ != Inclusive {
+            return None;
+        }
+
+        let single: RangeBound<UpperBound, T> =
+            Normalizable::normalize(RangeBound::new(lower.value.clone(), Inclusive));
+        if single == *upper {
+            Some(&lower.value)
+        } else {
+            None
+        }
+    }
+
+    /// Clamps a value into this range.
+    ///
+    /// Returns the value itself if the range contains it, and otherwise the
+    /// value in the range nearest to it. For a discrete type like `i32`, an
+    /// exclusive bound clamps to the nearest value it includes, so `15` clamps
+    /// into `[10,15)` as `14`.
+    ///
+    /// Returns `None` if the range is empty, or if the value lies beyond an
+    /// exclusive bound of a continuous type like `Timespec`. There is no
+    /// nearest value inside `[t1,t2)` to a value after `t2`, since any value
+    /// before `t2` has another value between it and `t2`.
+    pub fn clamp(&self, value: T) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        match self.lower() {
+            Some(lower) if !lower.in_bounds(&value) =>
+                return Normalizable::nearest_included(lower.clone()),
+            _ => {}
+        }
+
+        match self.upper() {
+            Some(upper) if !upper.in_bounds(&value) =>
+                return Normalizable::nearest_included(upper.clone()),
+            _ => {}
+        }
+
+        Some(value)
+    }
+
+    /// Widens this range as little as possible so that it contains `value`.
+    ///
+    /// Nothing changes if the range already contains `value`. Otherwise the
+    /// bound on the side of `value` is replaced by an inclusive bound at
+    /// `value`, and the range is normalized again. An unbounded side already
+    /// covers every value on that side, so it is never changed. An empty range
+    /// becomes the range containing only `value`.
+    pub fn extend_to_include(&mut self, value: T) {
+        if self.contains(&value) {
+            return;
+        }
+
+        let (lower, upper) = match mem::replace(&mut self.inner, Empty) {
+            Empty => {
+                *self = Range::singleton(value);
+                return;
+            }
+            Normal(lower, upper) => (lower, upper),
+        };
+
+        let below = match lower {
+            Some(ref lower) => !lower.in_bounds(&value),
+            None => false,
+        };
+        *self = if below {
+            Range::new(Some(RangeBound::new(value, Inclusive)), upper)
+        } else {
+            Range::new(lower, Some(RangeBound::new(value, Inclusive)))
+        };
+    }
+
     /// Returns the intersection of this range with another
     pub fn intersect(&self, other: &Range<T>) -> Range<T> {
         if self.is_empty() || other.is_empty() {
@@ -414,6 +1024,27 @@ impl<T: PartialOrd+Normalizable+Clone> Range<T> {
         Range::new(lower.map(|v| v.clone()), upper.map(|v| v.clone()))
     }
 
+    /// Like `intersect`, but also reports how the two ranges relate.
+    ///
+    /// Since an empty range is contained by every range, intersecting a
+    /// non-empty range with an empty one reports `SelfContainsOther` or
+    /// `OtherContainsSelf` rather than `Disjoint`.
+    pub fn intersect_ext(&self, other: &Range<T>) -> (Range<T>, IntersectKind) {
+        let kind = if self == other {
+            Same
+        } else if self.contains_range(other) {
+            SelfContainsOther
+        } else if other.contains_range(self) {
+            OtherContainsSelf
+        } else {
+            let intersection = self.intersect(other);
+            let kind = if intersection.is_empty() { Disjoint } else { Partial };
+            return (intersection, kind);
+        };
+
+        (self.intersect(other), kind)
+    }
+
     /// Returns the union of this range with another if it is contiguous
     pub fn union(&self, other: &Range<T>) -> Option<Range<T>> {
         if self.is_empty() {
@@ -429,19 +1060,270 @@ impl<T: PartialOrd+Normalizable+Clone> Range<T> {
         let (OptBound(l_upper), OptBound(u_upper)) =
             order(OptBound(self.upper()), OptBound(other.upper()));
 
-        let discontiguous = match (u_lower, l_upper) {
-            (Some(&RangeBound { value: ref l, type_: Exclusive }),
-             Some(&RangeBound { value: ref u, type_: Exclusive })) => l >= u,
-            (Some(&RangeBound { value: ref l, .. }),
-             Some(&RangeBound { value: ref u, .. })) => l > u,
-            _ => false
-        };
-
-        if discontiguous {
+        if discontiguous(u_lower, l_upper) {
             None
         } else {
             Some(Range::new(l_lower.map(|v| v.clone()),
                             u_upper.map(|v| v.clone())))
         }
     }
+
+    /// Returns the parts of `universe` which are not covered by this range.
+    ///
+    /// The result contains zero, one, or two non-empty ranges in ascending
+    /// order. If this range covers all of `universe`, the result is empty, and
+    /// if this range is empty or does not overlap `universe`, the result
+    /// contains only `universe`.
+    pub fn complement_within(&self, universe: &Range<T>) -> Vec<Range<T>> {
+        if universe.is_empty() {
+            return vec![];
+        }
+
+        if self.is_empty() {
+            return vec![universe.clone()];
+        }
+
+        let mut ranges = vec![];
+
+        match self.lower() {
+            Some(lower) => {
+                let below = Range::new(None, Some(RangeBound::new(lower.value.clone(),
+                                                                  flip(lower.type_))));
+                let below = universe.intersect(&below);
+                if !below.is_empty() {
+                    ranges.push(below);
+                }
+            }
+            None => {}
+        }
+
+        match self.upper() {
+            Some(upper) => {
+                let above = Range::new(Some(RangeBound::new(upper.value.clone(),
+                                                            flip(upper.type_))), None);
+                let above = universe.intersect(&above);
+                if !above.is_empty() {
+                    ranges.push(above);
+                }
+            }
+            None => {}
+        }
+
+        ranges
+    }
+}
+
+macro_rules! discrete_len(
+    ($t:ident) => (
+        impl Range<$t> {
+            /// Returns the number of values in the range, or `None` if it is
+            /// unbounded on either side.
+            ///
+            /// Since the range is normalized to `[lower,upper)`, this is
+            /// `upper - lower`. The difference is computed in `u64`, where it
+            /// always fits, so the count is exact even for a range spanning
+            /// the whole type.
+            pub fn len(&self) -> Option<u64> {
+                match self.inner {
+                    Empty => Some(0),
+                    // upper > lower, so the wrapping subtraction of the two's
+                    // complement representations gives the distance
+                    Normal(Some(ref lower), Some(ref upper)) =>
+                        Some(upper.value as u64 - lower.value as u64),
+                    Normal(..) => None,
+                }
+            }
+        }
+    )
+)
+
+discrete_len!(i32)
+discrete_len!(i64)
+
+static CACHE_EMPTY: u8           = 0b0000_0001;
+static CACHE_LOWER_BOUNDED: u8   = 0b0000_0010;
+static CACHE_LOWER_INCLUSIVE: u8 = 0b0000_0100;
+static CACHE_UPPER_BOUNDED: u8   = 0b0000_1000;
+static CACHE_UPPER_INCLUSIVE: u8 = 0b0001_0000;
+
+/// A type with a fixed size byte representation, used by `Range::to_bytes`
+/// and `Range::from_bytes`.
+pub trait FixedWidth {
+    /// Writes the value to `w`.
+    fn write_fixed<W: Writer>(&self, w: &mut W) -> IoResult<()>;
+
+    /// Reads a value written by `write_fixed` from `r`.
+    fn read_fixed<R: Reader>(r: &mut R) -> IoResult<Self>;
+}
+
+impl FixedWidth for i32 {
+    fn write_fixed<W: Writer>(&self, w: &mut W) -> IoResult<()> {
+        w.write_be_i32(*self)
+    }
+
+    fn read_fixed<R: Reader>(r: &mut R) -> IoResult<i32> {
+        r.read_be_i32()
+    }
+}
+
+impl FixedWidth for i64 {
+    fn write_fixed<W: Writer>(&self, w: &mut W) -> IoResult<()> {
+        w.write_be_i64(*self)
+    }
+
+    fn read_fixed<R: Reader>(r: &mut R) -> IoResult<i64> {
+        r.read_be_i64()
+    }
+}
+
+impl FixedWidth for Timespec {
+    fn write_fixed<W: Writer>(&self, w: &mut W) -> IoResult<()> {
+        try!(w.write_be_i64(self.sec));
+        w.write_be_i32(self.nsec)
+    }
+
+    fn read_fixed<R: Reader>(r: &mut R) -> IoResult<Timespec> {
+        let sec = try!(r.read_be_i64());
+        let nsec = try!(r.read_be_i32());
+        if nsec < 0 || nsec >= 1_000_000_000 {
+            return Err(invalid_cache_data("nanoseconds out of range"));
+        }
+        Ok(Timespec::new(sec, nsec))
+    }
+}
+
+fn invalid_cache_data(desc: &'static str) -> IoError {
+    IoError {
+        kind: InvalidInput,
+        desc: desc,
+        detail: None,
+    }
+}
+
+impl<T: PartialOrd+Normalizable+FixedWidth> Range<T> {
+    /// Encodes the range in a compact binary format, suitable for storing in
+    /// a cache.
+    ///
+    /// This format is independent of the Postgres wire format. It consists of
+    /// a flags byte, followed by the value of the lower bound and then the
+    /// value of the upper bound, if each is present.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (mut flags, lower, upper) = match self.inner {
+            Empty => (CACHE_EMPTY, None, None),
+            Normal(ref lower, ref upper) => (0, lower.as_ref(), upper.as_ref()),
+        };
+        match lower {
+            Some(lower) => {
+                flags |= CACHE_LOWER_BOUNDED;
+                if lower.type_ == Inclusive {
+                    flags |= CACHE_LOWER_INCLUSIVE;
+                }
+            }
+            None => {}
+        }
+        match upper {
+            Some(upper) => {
+                flags |= CACHE_UPPER_BOUNDED;
+                if upper.type_ == Inclusive {
+                    flags |= CACHE_UPPER_INCLUSIVE;
+                }
+            }
+            None => {}
+        }
+
+        // Writes to a MemWriter cannot fail
+        let mut buf = MemWriter::new();
+        buf.write_u8(flags).unwrap();
+        for lower in lower.iter() {
+            lower.value.write_fixed(&mut buf).unwrap();
+        }
+        for upper in upper.iter() {
+            upper.value.write_fixed(&mut buf).unwrap();
+        }
+        buf.unwrap()
+    }
+
+    /// Decodes a range encoded by `Range::to_bytes`.
+    ///
+    /// Returns an `InvalidInput` error if `buf` is not a valid encoding, and
+    /// an `EndOfFile` error if it is truncated.
+    pub fn from_bytes(buf: &[u8]) -> IoResult<Range<T>> {
+        let mut rdr = BufReader::new(buf);
+        let flags = try!(rdr.read_u8());
+        if flags & !(CACHE_EMPTY | CACHE_LOWER_BOUNDED | CACHE_LOWER_INCLUSIVE
+                     | CACHE_UPPER_BOUNDED | CACHE_UPPER_INCLUSIVE) != 0 {
+            return Err(invalid_cache_data("unknown range flags"));
+        }
+
+        let range = if flags & CACHE_EMPTY != 0 {
+            if flags != CACHE_EMPTY {
+                return Err(invalid_cache_data("empty range with bounds"));
+            }
+            Range::empty()
+        } else {
+            let lower = if flags & CACHE_LOWER_BOUNDED != 0 {
+                let type_ = if flags & CACHE_LOWER_INCLUSIVE != 0 {
+                    Inclusive
+                } else {
+                    Exclusive
+                };
+                let bound = RangeBound::new(try!(FixedWidth::read_fixed(&mut rdr)), type_);
+                if !Normalizable::can_normalize(&bound) {
+                    return Err(invalid_cache_data("invalid lower bound"));
+                }
+                Some(bound)
+            } else {
+                None
+            };
+            let upper = if flags & CACHE_UPPER_BOUNDED != 0 {
+                let type_ = if flags & CACHE_UPPER_INCLUSIVE != 0 {
+                    Inclusive
+                } else {
+                    Exclusive
+                };
+                let bound = RangeBound::new(try!(FixedWidth::read_fixed(&mut rdr)), type_);
+                if !Normalizable::can_normalize(&bound) {
+                    return Err(invalid_cache_data("invalid upper bound"));
+                }
+                Some(bound)
+            } else {
+                None
+            };
+            Range::new(lower, upper)
+        };
+
+        if !rdr.eof() {
+            return Err(invalid_cache_data("trailing data after range"));
+        }
+        Ok(range)
+    }
+}
+
+/// Coalesces a collection of ranges into a minimal set of disjoint ranges.
+///
+/// The ranges are sorted and any which overlap or are adjacent are merged.
+/// Empty ranges are dropped. The result is in ascending order and no two of
+/// its ranges overlap or are adjacent to each other.
+pub fn merge_all<T: Ord+Normalizable+Clone>(mut ranges: Vec<Range<T>>) -> Vec<Range<T>> {
+    ranges.sort();
+
+    let mut merged: Vec<Range<T>> = vec![];
+    for range in ranges.move_iter() {
+        if range.is_empty() {
+            continue;
+        }
+
+        let union = match merged.last() {
+            Some(last) => last.union(&range),
+            None => None,
+        };
+        match union {
+            Some(union) => {
+                merged.pop();
+                merged.push(union);
+            }
+            None => merged.push(range),
+        }
+    }
+    merged
 }
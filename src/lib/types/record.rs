@@ -0,0 +1,64 @@
+//! Anonymous composite values
+
+use PostgresResult;
+use error::{PgInvalidColumn, PgBadData};
+use types::{PostgresType, FromSql, RawFromSql};
+
+/// A composite value whose structure is not known ahead of time.
+///
+/// This corresponds to the Postgres `RECORD` type, which is the type of row
+/// constructors such as `ROW(1, 'x')` or `(1, 'x')`. Each field carries its
+/// own type, and fields are accessed by position.
+pub struct Record {
+    fields: Vec<(PostgresType, Option<Vec<u8>>)>,
+}
+
+impl Record {
+    /// Returns the number of fields in the record.
+    pub fn len(&self) -> uint {
+        self.fields.len()
+    }
+
+    /// Returns the type of the field at position `idx`, or `None` if the
+    /// record has fewer fields.
+    pub fn field_type<'a>(&'a self, idx: uint) -> Option<&'a PostgresType> {
+        self.fields.as_slice().get(idx).map(|&(ref ty, _)| ty)
+    }
+
+    /// Retrieves the value of the field at position `idx`.
+    ///
+    /// Returns `PgInvalidColumn` if the record has fewer fields, and an error
+    /// if the value cannot be converted to the requested type.
+    pub fn get<T: FromSql>(&self, idx: uint) -> PostgresResult<T> {
+        match self.fields.as_slice().get(idx) {
+            Some(&(ref ty, ref value)) => FromSql::from_sql(ty, value),
+            None => Err(PgInvalidColumn),
+        }
+    }
+}
+
+// Each field is its type's OID and a length prefixed value in the binary
+// format, with a length of -1 for NULL
+impl RawFromSql for Record {
+    fn raw_from_sql<R: Reader>(raw: &mut R) -> PostgresResult<Record> {
+        let count = try_pg!(raw.read_be_i32());
+        if count < 0 {
+            return Err(PgBadData);
+        }
+
+        // The count comes from the server, so nothing is allocated up front
+        // on the strength of it alone
+        let mut fields = Vec::new();
+        for _ in range(0, count) {
+            let ty = PostgresType::from_oid(try_pg!(raw.read_be_u32()));
+            let value = match try_pg!(raw.read_be_i32()) {
+                -1 => None,
+                len if len < 0 => return Err(PgBadData),
+                len => Some(try_pg!(raw.read_exact(len as uint))),
+            };
+            fields.push((ty, value));
+        }
+
+        Ok(Record { fields: fields })
+    }
+}
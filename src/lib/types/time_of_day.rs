@@ -0,0 +1,102 @@
+//! Time of day types
+
+use std::fmt;
+
+static USEC_PER_SEC: i64 = 1_000_000;
+static USEC_PER_DAY: i64 = 86_400 * USEC_PER_SEC;
+
+/// A time of day, with microsecond precision and no time zone.
+///
+/// This corresponds to the Postgres `TIME` type.
+#[deriving(PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct Time {
+    usecs: i64,
+}
+
+impl Time {
+    /// Creates a new time from an hour, minute, second and microsecond.
+    ///
+    /// Returns `None` if any of the components is out of range.
+    pub fn from_hms_micro(hour: uint, minute: uint, second: uint, usec: uint)
+                          -> Option<Time> {
+        if hour >= 24 || minute >= 60 || second >= 60 || usec >= USEC_PER_SEC as uint {
+            return None;
+        }
+        let secs = (hour * 3600 + minute * 60 + second) as i64;
+        Some(Time { usecs: secs * USEC_PER_SEC + usec as i64 })
+    }
+
+    /// Creates a new time from a number of microseconds since midnight.
+    ///
+    /// Returns `None` unless `usecs` is less than one day and not negative.
+    pub fn from_usecs(usecs: i64) -> Option<Time> {
+        if usecs < 0 || usecs >= USEC_PER_DAY {
+            None
+        } else {
+            Some(Time { usecs: usecs })
+        }
+    }
+
+    /// Returns the number of microseconds since midnight.
+    pub fn usecs(&self) -> i64 {
+        self.usecs
+    }
+
+    /// Returns the hour, from 0 to 23.
+    pub fn hour(&self) -> uint {
+        (self.usecs / (3600 * USEC_PER_SEC)) as uint
+    }
+
+    /// Returns the minute, from 0 to 59.
+    pub fn minute(&self) -> uint {
+        (self.usecs / (60 * USEC_PER_SEC) % 60) as uint
+    }
+
+    /// Returns the second, from 0 to 59.
+    pub fn second(&self) -> uint {
+        (self.usecs / USEC_PER_SEC % 60) as uint
+    }
+
+    /// Returns the fraction of the second in microseconds, from 0 to 999999.
+    pub fn microsecond(&self) -> uint {
+        (self.usecs % USEC_PER_SEC) as uint
+    }
+}
+
+impl fmt::Show for Time {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "{:02}:{:02}:{:02}", self.hour(), self.minute(), self.second()));
+        match self.microsecond() {
+            0 => Ok(()),
+            usec => write!(fmt, ".{:06}", usec),
+        }
+    }
+}
+
+/// A time of day with a time zone offset.
+///
+/// This corresponds to the Postgres `TIME WITH TIME ZONE` type.
+#[deriving(PartialEq, Eq, Clone)]
+pub struct TimeTz {
+    /// The local time of day
+    pub time: Time,
+    /// The offset of the time zone from UTC in seconds, positive to the east
+    /// of Greenwich.
+    pub offset: i32,
+}
+
+impl fmt::Show for TimeTz {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let (sign, offset) = if self.offset < 0 {
+            ('-', -self.offset)
+        } else {
+            ('+', self.offset)
+        };
+        try!(write!(fmt, "{}{}{:02}", self.time, sign, offset / 3600));
+        match offset % 3600 {
+            0 => Ok(()),
+            rest if rest % 60 == 0 => write!(fmt, ":{:02}", rest / 60),
+            rest => write!(fmt, ":{:02}:{:02}", rest / 60, rest % 60),
+        }
+    }
+}
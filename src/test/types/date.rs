@@ -0,0 +1,37 @@
+use postgres::types::date::Date;
+
+#[test]
+fn test_from_ymd() {
+    assert_eq!(0, Date::from_ymd(1970, 1, 1).unwrap().days());
+    assert_eq!(10957, Date::from_ymd(2000, 1, 1).unwrap().days());
+    assert_eq!(-1, Date::from_ymd(1969, 12, 31).unwrap().days());
+    assert_eq!(19782, Date::from_ymd(2024, 2, 29).unwrap().days());
+}
+
+#[test]
+fn test_from_ymd_invalid() {
+    assert!(Date::from_ymd(2023, 2, 29).is_none());
+    assert!(Date::from_ymd(1900, 2, 29).is_none());
+    assert!(Date::from_ymd(2000, 2, 30).is_none());
+    assert!(Date::from_ymd(2024, 4, 31).is_none());
+    assert!(Date::from_ymd(2024, 0, 1).is_none());
+    assert!(Date::from_ymd(2024, 13, 1).is_none());
+    assert!(Date::from_ymd(2024, 1, 0).is_none());
+    assert!(Date::from_ymd(2000, 2, 29).is_some());
+}
+
+#[test]
+fn test_ymd() {
+    for &(y, m, d) in [(1970i32, 1u, 1u), (2000, 2, 29), (2024, 2, 28), (2024, 3, 1),
+                       (1999, 12, 31), (1600, 3, 1), (-1, 12, 31)].iter() {
+        assert_eq!((y, m, d), Date::from_ymd(y, m, d).unwrap().ymd());
+    }
+    assert_eq!((2024, 3, 1), Date::from_days(Date::from_ymd(2024, 2, 29).unwrap().days() + 1).ymd());
+    assert_eq!((2025, 1, 1), Date::from_days(Date::from_ymd(2024, 12, 31).unwrap().days() + 1).ymd());
+}
+
+#[test]
+fn test_show() {
+    assert_eq!("2024-02-29", format!("{}", Date::from_ymd(2024, 2, 29).unwrap()).as_slice());
+    assert_eq!("0099-01-05", format!("{}", Date::from_ymd(99, 1, 5).unwrap()).as_slice());
+}
@@ -0,0 +1,139 @@
+use std::cmp;
+use std::i32;
+
+use postgres::types::range::{Range, RangeBound, Inclusive, Same, SelfContainsOther,
+                             OtherContainsSelf, merge_all};
+
+fn empty() -> Range<i32> {
+    Range::empty()
+}
+
+fn samples() -> Vec<Range<i32>> {
+    vec![range!('[' 1i32, 5i32 ')'),
+         range!('[' 3i32, ')'),
+         range!('(', 3i32 ']'),
+         range!('(', ')'),
+         Range::singleton(7i32)]
+}
+
+#[test]
+fn test_empty_bounds() {
+    let r = empty();
+    assert!(r.is_empty());
+    assert!(!r.is_full());
+    assert!(r.lower().is_none());
+    assert!(r.upper().is_none());
+    assert!(r.lower_type().is_none());
+    assert!(r.upper_type().is_none());
+    assert_eq!(None, r.lower_value());
+    assert_eq!(None, r.upper_value());
+    assert_eq!(None, r.as_singleton());
+}
+
+#[test]
+fn test_empty_construction() {
+    assert_eq!(empty(), range!('[' 5i32, 5i32 ')'));
+    assert_eq!(empty(), range!('[' 5i32, 1i32 ']'));
+    assert_eq!(empty(), Range::between(5i32, 5i32));
+    assert_eq!(empty(), Range::new(Some(RangeBound::new(2i32, Inclusive)),
+                                   Some(RangeBound::new(1i32, Inclusive))));
+}
+
+#[test]
+fn test_empty_contains() {
+    for v in [i32::MIN, -1, 0, 1, i32::MAX].iter() {
+        assert!(!empty().contains(v));
+    }
+}
+
+#[test]
+fn test_empty_contains_range() {
+    assert!(empty().contains_range(&empty()));
+    assert!(empty().is_contained_by(&empty()));
+    for r in samples().iter() {
+        assert!(r.contains_range(&empty()));
+        assert!(empty().is_contained_by(r));
+        assert!(!empty().contains_range(r));
+        assert!(!r.is_contained_by(&empty()));
+    }
+}
+
+#[test]
+fn test_empty_intersect() {
+    assert_eq!(empty(), empty().intersect(&empty()));
+    assert_eq!(empty(), empty().into_intersect(empty()));
+    for r in samples().iter() {
+        assert_eq!(empty(), empty().intersect(r));
+        assert_eq!(empty(), r.intersect(&empty()));
+        assert_eq!(empty(), empty().into_intersect(r.clone()));
+        assert_eq!(empty(), r.clone().into_intersect(empty()));
+    }
+}
+
+#[test]
+fn test_empty_intersect_ext() {
+    let (intersection, kind) = empty().intersect_ext(&empty());
+    assert_eq!(empty(), intersection);
+    assert!(kind == Same);
+    for r in samples().iter() {
+        let (intersection, kind) = r.intersect_ext(&empty());
+        assert_eq!(empty(), intersection);
+        assert!(kind == SelfContainsOther);
+        let (intersection, kind) = empty().intersect_ext(r);
+        assert_eq!(empty(), intersection);
+        assert!(kind == OtherContainsSelf);
+    }
+}
+
+#[test]
+fn test_empty_union() {
+    assert_eq!(Some(empty()), empty().union(&empty()));
+    assert_eq!(Some(empty()), empty().into_union(empty()));
+    for r in samples().iter() {
+        assert_eq!(Some(r.clone()), empty().union(r));
+        assert_eq!(Some(r.clone()), r.union(&empty()));
+        assert_eq!(Some(r.clone()), empty().into_union(r.clone()));
+        assert_eq!(Some(r.clone()), r.clone().into_union(empty()));
+    }
+}
+
+#[test]
+fn test_empty_complement_within() {
+    assert_eq!(Vec::<Range<i32>>::new(), empty().complement_within(&empty()));
+    for r in samples().iter() {
+        assert_eq!(vec![r.clone()], empty().complement_within(r));
+        assert_eq!(Vec::<Range<i32>>::new(), r.complement_within(&empty()));
+    }
+}
+
+#[test]
+fn test_empty_ordering() {
+    assert!(empty() == empty());
+    assert!(empty().cmp(&empty()) == cmp::Equal);
+    for r in samples().iter() {
+        assert!(empty() < *r);
+        assert!(*r > empty());
+    }
+}
+
+#[test]
+fn test_empty_map_clamp_extend() {
+    assert_eq!(empty(), empty().map(|v| v * 2));
+    assert_eq!(None, empty().clamp(3));
+
+    let mut r = empty();
+    r.extend_to_include(3);
+    assert_eq!(Range::singleton(3i32), r);
+}
+
+#[test]
+fn test_empty_merge_all() {
+    assert_eq!(Vec::<Range<i32>>::new(), merge_all(vec![empty(), empty()]));
+    assert_eq!(vec![range!('[' 1i32, 5i32 ')')],
+               merge_all(vec![empty(), range!('[' 1i32, 5i32 ')'), empty()]));
+}
+
+#[test]
+fn test_empty_show() {
+    assert_eq!("empty".to_str(), empty().to_str());
+}
@@ -1,2 +1,4 @@
 mod array;
+mod date;
+mod empty_range;
 mod range;
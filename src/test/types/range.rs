@@ -1,5 +1,18 @@
+use std::cmp;
+use std::f32;
+use std::f64;
 use std::i32;
-
+use std::i64;
+use std::io::InvalidInput;
+use std::rand;
+use std::rand::Rng;
+use std::u32;
+use std::u64;
+use serialize::json;
+use time;
+use time::Timespec;
+
+use postgres::types::date::Date;
 use postgres::types::range::{RangeBound,
                              Range,
                              Inclusive,
@@ -7,7 +20,14 @@ use postgres::types::range::{RangeBound,
                              UpperBound,
                              LowerBound,
                              Normalizable,
-                             BoundType};
+                             BoundType,
+                             Disjoint,
+                             Same,
+                             SelfContainsOther,
+                             OtherContainsSelf,
+                             Partial,
+                             FixedWidth,
+                             merge_all};
 
 #[test]
 fn test_range_bound_lower_lt() {
@@ -121,6 +141,23 @@ fn test_normalize_upper() {
     assert_eq!(RangeBound::new(10i32, Exclusive), Normalizable::normalize(r));
 }
 
+#[test]
+fn test_normalize_date() {
+    fn d(year: i32, month: uint, day: uint) -> Date {
+        Date::from_ymd(year, month, day).unwrap()
+    }
+
+    assert_eq!(range!('[' d(2024, 2, 28), d(2024, 3, 1) ')'),
+               range!('[' d(2024, 2, 28), d(2024, 2, 29) ']'));
+    assert_eq!(range!('[' d(2023, 3, 1), d(2024, 1, 1) ')'),
+               range!('(' d(2023, 2, 28), d(2023, 12, 31) ']'));
+    assert!(range!('(' d(2024, 2, 28), d(2024, 2, 29) ')').is_empty());
+
+    let r = range!('[' d(2024, 2, 29), d(2024, 2, 29) ']');
+    assert_eq!(d(2024, 3, 1), r.upper().unwrap().value);
+    assert!(r.upper().unwrap().type_ == Exclusive);
+}
+
 #[test]
 fn test_range_normalizes() {
     let r1 = range!('(' 10i32, 15i32 ']');
@@ -128,6 +165,27 @@ fn test_range_normalizes() {
     assert_eq!(r1, r2);
 }
 
+#[test]
+fn test_discrete_equality() {
+    let forms = [range!('[' 1i32, 5i32 ']'), range!('[' 1i32, 6i32 ')'),
+                 range!('(' 0i32, 5i32 ']'), range!('(' 0i32, 6i32 ')')];
+    for a in forms.iter() {
+        for b in forms.iter() {
+            assert!(a == b);
+            assert!(a.cmp(b) == cmp::Equal);
+        }
+        assert_eq!(1, a.lower().unwrap().value);
+        assert!(a.lower().unwrap().type_ == Inclusive);
+        assert_eq!(6, a.upper().unwrap().value);
+        assert!(a.upper().unwrap().type_ == Exclusive);
+    }
+
+    assert!(range!('[' 1i64, 5i64 ']') == range!('(' 0i64, 6i64 ')'));
+    assert!(range!('(' 0i32, ']') == range!('[' 1i32, ')'));
+    assert!(range!('(', 5i32 ']') == range!('(', 6i32 ')'));
+    assert!(range!('(' 1i32, 2i32 ')') == range!('[' 7i32, 3i32 ']'));
+}
+
 #[test]
 fn test_range_empty() {
     assert!((range!('(' 9i32, 10i32 ')')).is_empty());
@@ -136,6 +194,68 @@ fn test_range_empty() {
     assert!((range!('[' 10i32, 9i32 ']')).is_empty());
 }
 
+#[test]
+fn test_bound_values() {
+    let r = range!('(' 1i32, 10i32 ']');
+    assert_eq!(Some(2), r.lower_value());
+    assert!(r.lower_type() == Some(Inclusive));
+    assert_eq!(Some(11), r.upper_value());
+    assert!(r.upper_type() == Some(Exclusive));
+
+    let r = range!('(', 10i32 ')');
+    assert_eq!(None, r.lower_value());
+    assert!(r.lower_type().is_none());
+    assert_eq!(Some(10), r.upper_value());
+
+    let r = Range::<i32>::empty();
+    assert_eq!(None, r.lower_value());
+    assert_eq!(None, r.upper_value());
+    assert!(r.upper_type().is_none());
+}
+
+#[test]
+fn test_range_full() {
+    assert!((range!('(', ')')).is_full());
+    assert!(!Range::<i32>::empty().is_full());
+    assert!(!(range!('(', 5i32 ']')).is_full());
+    assert!(!(range!('[' 5i32, ')')).is_full());
+    assert!(!(range!('[' 5i32, 10i32 ')')).is_full());
+}
+
+#[test]
+fn test_discrete_single_element() {
+    let r = range!('[' 5i32, 5i32 ']');
+    assert!(!r.is_empty());
+    assert_eq!(range!('[' 5i32, 6i32 ')'), r);
+    assert_eq!("[5,6)", format!("{}", r).as_slice());
+    assert!(r.contains(&5));
+    assert!(!r.contains(&4));
+    assert!(!r.contains(&6));
+
+    let r = range!('(' 4i32, 6i32 ')');
+    assert_eq!(range!('[' 5i32, 6i32 ')'), r);
+    assert!(r.contains(&5));
+
+    assert!(range!('[' 5i32, 5i32 ')').is_empty());
+    assert!(range!('(' 5i32, 5i32 ']').is_empty());
+    assert!(range!('(' 5i32, 5i32 ')').is_empty());
+    assert!(range!('(' 5i32, 6i32 ')').is_empty());
+    assert!(!range!('[' 5i32, 5i32 ')').contains(&5));
+}
+
+#[test]
+fn test_continuous_single_point() {
+    fn t(sec: i64) -> Timespec {
+        Timespec::new(sec, 0)
+    }
+
+    let r = range!('[' t(5), t(5) ']');
+    assert!(!r.is_empty());
+    assert!(r.contains(&t(5)));
+    assert!(range!('[' t(5), t(5) ')').is_empty());
+    assert!(range!('(' t(5), t(5) ']').is_empty());
+}
+
 #[test]
 fn test_intersection() {
     let r1 = range!('[' 10i32, 15i32 ')');
@@ -211,3 +331,609 @@ fn test_contains_range() {
     assert!(!r1.contains_range(&r2));
     assert!(r2.contains_range(&r1));
 }
+
+#[test]
+fn test_is_contained_by() {
+    let empty = Range::<i32>::empty();
+    assert!(empty.is_contained_by(&empty));
+
+    let r1 = range!('[' 10i32, 15i32 ')');
+    assert!(r1.is_contained_by(&r1));
+    assert!(empty.is_contained_by(&r1));
+    assert!(!r1.is_contained_by(&empty));
+
+    let r2 = range!('(' 10i32, ')');
+    assert!(!r1.is_contained_by(&r2));
+    assert!(!r2.is_contained_by(&r1));
+
+    let r2 = range!('(', 15i32 ']');
+    assert!(r1.is_contained_by(&r2));
+    assert!(!r2.is_contained_by(&r1));
+    assert!(empty.is_contained_by(&r2));
+}
+
+#[test]
+fn test_complement_within() {
+    let universe = range!('[' 0i32, 100i32 ')');
+
+    let r = range!('[' 10i32, 20i32 ']');
+    assert_eq!(vec![range!('[' 0i32, 10i32 ')'), range!('(' 20i32, 100i32 ')')],
+               r.complement_within(&universe));
+
+    let r = range!('(', 20i32 ']');
+    assert_eq!(vec![range!('(' 20i32, 100i32 ')')], r.complement_within(&universe));
+
+    let r = range!('[' 50i32, ')');
+    assert_eq!(vec![range!('[' 0i32, 50i32 ')')], r.complement_within(&universe));
+
+    let r = range!('[' 0i32, 50i32 ')');
+    assert_eq!(vec![range!('[' 50i32, 100i32 ')')], r.complement_within(&universe));
+
+    assert_eq!(Vec::<Range<i32>>::new(), range!('(', ')').complement_within(&universe));
+    assert_eq!(Vec::<Range<i32>>::new(), universe.complement_within(&universe));
+    assert_eq!(Vec::<Range<i32>>::new(),
+               range!('[' -10i32, 200i32 ')').complement_within(&universe));
+
+    assert_eq!(vec![universe.clone()], Range::empty().complement_within(&universe));
+    assert_eq!(vec![universe.clone()],
+               range!('[' 200i32, 300i32 ')').complement_within(&universe));
+    assert_eq!(vec![universe.clone()],
+               range!('(', -10i32 ')').complement_within(&universe));
+
+    assert_eq!(Vec::<Range<i32>>::new(), r.complement_within(&Range::empty()));
+}
+
+#[test]
+fn test_complement_within_continuous() {
+    fn t(sec: i64) -> Timespec {
+        Timespec::new(sec, 0)
+    }
+
+    let universe = range!('[' t(0), t(10) ']');
+    let r = range!('(' t(2), t(5) ')');
+    assert_eq!(vec![range!('[' t(0), t(2) ']'), range!('[' t(5), t(10) ']')],
+               r.complement_within(&universe));
+}
+
+#[test]
+fn test_range_show() {
+    assert_eq!("empty", format!("{}", Range::<i32>::empty()).as_slice());
+    let r: Range<i32> = range!('(', ')');
+    assert_eq!("(,)", format!("{}", r).as_slice());
+    assert_eq!("[10,)", format!("{}", range!('(' 9i32, ')')).as_slice());
+    assert_eq!("(,11)", format!("{}", range!('(', 10i32 ']')).as_slice());
+    assert_eq!("(,10)", format!("{}", range!('(', 10i32 ')')).as_slice());
+    assert_eq!("[1,11)", format!("{}", range!('[' 1i32, 10i32 ']')).as_slice());
+}
+
+#[test]
+fn test_display_with() {
+    fn hex(v: &i32) -> String {
+        format!("{:x}", *v).as_slice().to_ascii_upper()
+    }
+
+    assert_eq!("[A,FF)", range!('[' 10i32, 255i32 ')').display_with(hex).as_slice());
+    assert_eq!("(,FF)", range!('(', 255i32 ')').display_with(hex).as_slice());
+    assert_eq!("[A,)", range!('[' 10i32, ')').display_with(hex).as_slice());
+    assert_eq!("empty", Range::<i32>::empty().display_with(hex).as_slice());
+
+    fn t(sec: i64) -> Timespec {
+        Timespec::new(sec, 0)
+    }
+    let r = range!('(' t(0), t(86400) ']');
+    assert_eq!("(1970-01-01,1970-01-02]",
+               r.display_with(|v| format!("{}", time::at_utc(*v).strftime("%Y-%m-%d")))
+                .as_slice());
+}
+
+#[test]
+fn test_into_intersect() {
+    let r1 = range!('[' 10i32, 15i32 ')');
+    let checks = [range!('(' 20i32, 25i32 ']'),
+                  range!('(', ')'),
+                  range!('(' 10i32, ')'),
+                  range!('(', 15i32 ']'),
+                  range!('[' 11i32, 14i32 ')'),
+                  range!(empty)];
+    for r2 in checks.iter() {
+        assert_eq!(r1.intersect(r2), r1.clone().into_intersect(r2.clone()));
+        assert_eq!(r2.intersect(&r1), r2.clone().into_intersect(r1.clone()));
+    }
+}
+
+#[test]
+fn test_into_union() {
+    let r1 = range!('[' 10i32, 15i32 ')');
+    let checks = [range!('(' 20i32, 25i32 ']'),
+                  range!('(', ')'),
+                  range!('[' 13i32, 50i32 ')'),
+                  range!('(', 11i32 ')'),
+                  range!('(' 15i32, 20i32 ')'),
+                  range!('[' 15i32, 20i32 ']'),
+                  range!(empty)];
+    for r2 in checks.iter() {
+        assert_eq!(r1.union(r2), r1.clone().into_union(r2.clone()));
+        assert_eq!(r2.union(&r1), r2.clone().into_union(r1.clone()));
+    }
+}
+
+#[test]
+fn test_singleton_discrete() {
+    let r = Range::singleton(5i32);
+    assert_eq!(range!('[' 5i32, 6i32 ')'), r);
+    assert_eq!(Some(&5), r.as_singleton());
+    assert_eq!(Some(&5), range!('(' 4i32, 5i32 ']').as_singleton());
+    assert_eq!(None, range!('[' 5i32, 7i32 ')').as_singleton());
+    assert_eq!(None, range!('[' 5i32, ')').as_singleton());
+    assert_eq!(None, Range::<i32>::empty().as_singleton());
+
+    let d = Date::from_ymd(2014, 2, 28).unwrap();
+    assert_eq!(Some(&d), Range::singleton(d.clone()).as_singleton());
+}
+
+#[test]
+fn test_singleton_continuous() {
+    fn t(sec: i64) -> Timespec {
+        Timespec::new(sec, 0)
+    }
+
+    let r = Range::singleton(t(5));
+    assert_eq!(range!('[' t(5), t(5) ']'), r);
+    assert_eq!(Some(&t(5)), r.as_singleton());
+    assert_eq!(None, range!('[' t(5), t(6) ')').as_singleton());
+    assert_eq!(None, range!('[' t(5), t(6) ']').as_singleton());
+    assert_eq!(None, range!('(', t(5) ']').as_singleton());
+}
+
+#[test]
+fn test_intersect_ext() {
+    let r1 = range!('[' 10i32, 15i32 ')');
+
+    assert_eq!((Range::empty(), Disjoint), r1.intersect_ext(&range!('(' 20i32, 25i32 ']')));
+    assert_eq!((r1.clone(), Same), r1.intersect_ext(&range!('[' 10i32, 14i32 ']')));
+    assert_eq!((range!('[' 11i32, 14i32 ')'), SelfContainsOther),
+               r1.intersect_ext(&range!('[' 11i32, 14i32 ')')));
+    assert_eq!((r1.clone(), OtherContainsSelf), r1.intersect_ext(&range!('(', ')')));
+    assert_eq!((range!('[' 12i32, 15i32 ')'), Partial),
+               r1.intersect_ext(&range!('[' 12i32, 20i32 ')')));
+    assert_eq!((Range::empty(), SelfContainsOther), r1.intersect_ext(&Range::empty()));
+    assert_eq!((Range::empty(), OtherContainsSelf), Range::empty().intersect_ext(&r1));
+    assert_eq!((Range::<i32>::empty(), Same), Range::empty().intersect_ext(&Range::empty()));
+
+    let r2 = range!('[' 20i32, 30i32 ')');
+    for other in [range!('(', ')'), range!('[' 25i32, ')'), range!('[' 22i32, 24i32 ')'),
+                  range!('(', 20i32 ')'), range!(empty)].iter() {
+        let (intersection, _) = r2.intersect_ext(other);
+        assert_eq!(r2.intersect(other), intersection);
+    }
+}
+
+#[test]
+fn test_clamp_discrete() {
+    let r = range!('[' 10i32, 15i32 ')');
+    assert_eq!(Some(12), r.clamp(12));
+    assert_eq!(Some(10), r.clamp(10));
+    assert_eq!(Some(10), r.clamp(i32::MIN));
+    assert_eq!(Some(14), r.clamp(15));
+    assert_eq!(Some(14), r.clamp(100));
+
+    let r = range!('(' 10i32, 15i32 ']');
+    assert_eq!(Some(11), r.clamp(10));
+    assert_eq!(Some(15), r.clamp(16));
+
+    let r = range!('(', 15i32 ')');
+    assert_eq!(Some(i32::MIN), r.clamp(i32::MIN));
+    assert_eq!(Some(14), r.clamp(20));
+
+    assert_eq!(None, Range::<i32>::empty().clamp(10));
+
+    let r = range!('[' Date::from_ymd(2014, 1, 1).unwrap(), Date::from_ymd(2014, 2, 1).unwrap() ')');
+    assert_eq!(Date::from_ymd(2014, 1, 31), r.clamp(Date::from_ymd(2014, 3, 1).unwrap()));
+}
+
+#[test]
+fn test_clamp_continuous() {
+    fn t(sec: i64) -> Timespec {
+        Timespec::new(sec, 0)
+    }
+
+    let r = range!('[' t(10), t(15) ')');
+    assert_eq!(Some(t(12)), r.clamp(t(12)));
+    assert_eq!(Some(t(10)), r.clamp(t(5)));
+    assert_eq!(None, r.clamp(t(15)));
+    assert_eq!(None, r.clamp(t(20)));
+
+    let r = range!('(' t(10), t(15) ']');
+    assert_eq!(None, r.clamp(t(5)));
+    assert_eq!(Some(t(15)), r.clamp(t(20)));
+
+    let r = range!('(', ')');
+    assert_eq!(Some(t(20)), r.clamp(t(20)));
+}
+
+#[test]
+fn test_nearest_included() {
+    let b: RangeBound<UpperBound, i32> = RangeBound::new(10, Exclusive);
+    assert_eq!(Some(9), Normalizable::nearest_included(b));
+    let b: RangeBound<LowerBound, i32> = RangeBound::new(10, Exclusive);
+    assert_eq!(Some(11), Normalizable::nearest_included(b));
+    let b: RangeBound<LowerBound, i32> = RangeBound::new(i32::MAX, Exclusive);
+    assert_eq!(None, Normalizable::nearest_included(b));
+    let b: RangeBound<UpperBound, Timespec> = RangeBound::new(Timespec::new(10, 0), Inclusive);
+    assert_eq!(Some(Timespec::new(10, 0)), Normalizable::nearest_included(b));
+    let b: RangeBound<UpperBound, Timespec> = RangeBound::new(Timespec::new(10, 0), Exclusive);
+    assert_eq!(None, Normalizable::nearest_included(b));
+}
+
+// Range comparison and containment are built on the ordering of optional
+// bounds, where a missing lower bound is less than every lower bound and a
+// missing upper bound is greater than every upper bound.
+#[test]
+fn test_unbounded_side_ordering() {
+    fn check(a: Range<i32>, b: Range<i32>, expected: cmp::Ordering) {
+        assert!(a.cmp(&b) == expected, "{} cmp {}", a, b);
+        assert!(a.partial_cmp(&b) == Some(expected), "{} partial_cmp {}", a, b);
+        assert!(b.cmp(&a) == expected.reverse(), "{} cmp {}", b, a);
+    }
+
+    fn lower(bound: Option<i32>) -> Range<i32> {
+        Range::new(bound.map(|v| RangeBound::new(v, Inclusive)),
+                   Some(RangeBound::new(100, Exclusive)))
+    }
+
+    fn upper(bound: Option<i32>) -> Range<i32> {
+        Range::new(Some(RangeBound::new(0, Inclusive)),
+                   bound.map(|v| RangeBound::new(v, Exclusive)))
+    }
+
+    // lower bounds
+    check(lower(None), lower(None), cmp::Equal);
+    check(lower(None), lower(Some(5)), cmp::Less);
+    check(lower(None), lower(Some(i32::MIN)), cmp::Less);
+    check(lower(Some(5)), lower(Some(5)), cmp::Equal);
+    check(lower(Some(5)), lower(Some(6)), cmp::Less);
+
+    // upper bounds
+    check(upper(None), upper(None), cmp::Equal);
+    check(upper(None), upper(Some(5)), cmp::Greater);
+    check(upper(None), upper(Some(i32::MAX)), cmp::Greater);
+    check(upper(Some(5)), upper(Some(5)), cmp::Equal);
+    check(upper(Some(5)), upper(Some(6)), cmp::Less);
+
+    // the lower bound is compared first
+    check(range!('(', ')'), range!('[' 0i32, ')'), cmp::Less);
+    check(range!('(', ')'), range!('(', 5i32 ')'), cmp::Greater);
+    check(range!('(', 5i32 ')'), range!('[' 0i32, ')'), cmp::Less);
+
+    // containment
+    assert!((range!('(', ')')).contains_range(&range!('[' 1i32, 5i32 ')')));
+    assert!((range!('(', ')')).contains_range(&range!('(', ')')));
+    assert!((range!('(', 5i32 ')')).contains_range(&range!('(', 3i32 ')')));
+    assert!(!(range!('(', 3i32 ')')).contains_range(&range!('(', 5i32 ')')));
+    assert!(!(range!('[' 1i32, 5i32 ')')).contains_range(&range!('(', 5i32 ')')));
+    assert!((range!('[' 1i32, ')')).contains_range(&range!('[' 2i32, ')')));
+    assert!(!(range!('[' 2i32, ')')).contains_range(&range!('[' 1i32, ')')));
+    assert!(!(range!('[' 1i32, 5i32 ')')).contains_range(&range!('[' 1i32, ')')));
+
+    // intersection and union
+    assert_eq!(range!('[' 1i32, 5i32 ')'),
+               (range!('(', 5i32 ')')).intersect(&range!('[' 1i32, ')')));
+    assert_eq!(range!('(', 5i32 ')'),
+               (range!('(', 5i32 ')')).intersect(&range!('(', ')')));
+    assert_eq!(Some(range!('(', ')')),
+               (range!('(', 5i32 ')')).union(&range!('[' 1i32, ')')));
+}
+
+#[test]
+fn test_range_ord() {
+    let mut ranges = vec!(range!('[' 5i32, 10i32 ')'),
+                          range!('[' 1i32, ')'),
+                          range!(empty),
+                          range!('[' 1i32, 5i32 ')'),
+                          range!('(', 3i32 ')'),
+                          range!('(' 1i32, 5i32 ')'),
+                          range!('[' 1i32, 3i32 ')'));
+    ranges.sort();
+    assert!(ranges == vec!(range!(empty),
+                           range!('(', 3i32 ')'),
+                           range!('[' 1i32, 3i32 ')'),
+                           range!('[' 1i32, 5i32 ')'),
+                           range!('[' 1i32, ')'),
+                           range!('(' 1i32, 5i32 ')'),
+                           range!('[' 5i32, 10i32 ')')));
+
+    assert!(range!('[' 1i32, 5i32 ']') == range!('[' 1i32, 6i32 ')'));
+    assert!(range!('[' 1i32, 5i32 ']').cmp(&range!('[' 1i32, 6i32 ')')) == cmp::Equal);
+
+    fn t(sec: i64) -> Timespec {
+        Timespec::new(sec, 0)
+    }
+
+    assert!(range!('[' t(1), t(5) ')') < range!('[' t(1), t(5) ']'));
+    assert!(range!('[' t(1), t(5) ']') < range!('(' t(1), t(5) ']'));
+    assert!(range!(empty) < range!('[' t(1), t(1) ']'));
+}
+
+#[test]
+fn test_merge_all() {
+    let ranges = vec!(range!('[' 10i32, 12i32 ')'),
+                      range!('[' 1i32, 3i32 ')'),
+                      range!(empty),
+                      range!('[' 3i32, 5i32 ']'),
+                      range!('[' 20i32, 25i32 ')'),
+                      range!('(' 2i32, 4i32 ')'),
+                      range!('[' 11i32, 15i32 ')'),
+                      range!('[' 22i32, 23i32 ')'));
+    assert!(merge_all(ranges) == vec!(range!('[' 1i32, 6i32 ')'),
+                                      range!('[' 10i32, 15i32 ')'),
+                                      range!('[' 20i32, 25i32 ')')));
+
+    let ranges: Vec<Range<i32>> = vec!(range!(empty), range!(empty));
+    assert!(merge_all(ranges).is_empty());
+
+    let ranges = vec!(range!('[' 5i32, ')'), range!('(', 1i32 ')'), range!('[' 0i32, 5i32 ')'));
+    assert!(merge_all(ranges) == vec!(range!('(', ')')));
+}
+
+#[test]
+fn test_float_range() {
+    let r = range!('[' 1.5f64, 5.0f64 ')');
+    assert!(r.contains(&1.5));
+    assert!(r.contains(&4.9));
+    assert!(!r.contains(&5.0));
+    assert!(range!('[' 1.5f64, 1.5f64 ')').is_empty());
+    assert_eq!(Some(&1.5f64), range!('[' 1.5f64, 1.5f64 ']').as_singleton());
+    assert!(range!('(', f64::INFINITY ')').contains(&1e300));
+}
+
+#[test]
+#[should_fail]
+fn test_nan_lower_bound() {
+    range!('[' f64::NAN, 5.0f64 ']');
+}
+
+#[test]
+#[should_fail]
+fn test_nan_upper_bound() {
+    range!('(', f32::NAN ')');
+}
+
+#[test]
+fn test_range_helpers() {
+    assert!(Range::at_least(5i32) == range!('[' 5i32, ')'));
+    assert!(Range::up_to(5i32) == range!('(', 5i32 ')'));
+    assert!(Range::up_to_inclusive(5i32) == range!('(', 6i32 ')'));
+    assert!(Range::between(1i32, 5i32) == range!('[' 1i32, 5i32 ')'));
+    assert!(Range::between_inclusive(1i32, 5i32) == range!('[' 1i32, 6i32 ')'));
+    assert!(Range::between(5i32, 5i32).is_empty());
+    assert!(Range::between(5i32, 1i32).is_empty());
+    assert!(!Range::between_inclusive(5i32, 5i32).is_empty());
+
+    let t = Timespec::new(10, 0);
+    assert!(Range::up_to_inclusive(t) == range!('(', t ']'));
+    assert!(Range::between_inclusive(t, t) == range!('[' t, t ']'));
+
+    let ranges: Vec<Range<i32>> = range(0i32, 3).map(|i| Range::between(i * 10, i * 10 + 5))
+                                                .collect();
+    assert!(ranges == vec![range!('[' 0i32, 5i32 ')'),
+                           range!('[' 10i32, 15i32 ')'),
+                           range!('[' 20i32, 25i32 ')')]);
+}
+
+#[test]
+fn test_range_macro_shorthand() {
+    assert!(range!(5i32 => 10i32) == range!('[' 5i32, 10i32 ')'));
+    assert!(range!(5i32 ..= 10i32) == range!('[' 5i32, 11i32 ')'));
+    assert!(range!(5i32 => 5i32).is_empty());
+    assert!(range!(5i32 ..= 4i32).is_empty());
+    assert_eq!(Some(&5i32), range!(5i32 ..= 5i32).as_singleton());
+
+    let t = Timespec::new(10, 0);
+    assert!(range!(t => t).is_empty());
+    assert!(!range!(t ..= t).is_empty());
+}
+
+#[test]
+fn test_extend_to_include() {
+    let mut r = range!('[' 5i32, 10i32 ')');
+    r.extend_to_include(7);
+    assert!(r == range!('[' 5i32, 10i32 ')'));
+    r.extend_to_include(10);
+    assert!(r == range!('[' 5i32, 11i32 ')'));
+    r.extend_to_include(2);
+    assert!(r == range!('[' 2i32, 11i32 ')'));
+
+    let mut r = Range::empty();
+    r.extend_to_include(3i32);
+    assert!(r == range!('[' 3i32, 4i32 ')'));
+
+    let mut r = range!('(', 10i32 ')');
+    r.extend_to_include(-100);
+    assert!(r == range!('(', 10i32 ')'));
+    r.extend_to_include(20);
+    assert!(r == range!('(', 21i32 ')'));
+
+    fn t(sec: i64) -> Timespec {
+        Timespec::new(sec, 0)
+    }
+
+    let mut r = range!('(' t(10), t(20) ')');
+    r.extend_to_include(t(10));
+    assert!(r == range!('[' t(10), t(20) ')'));
+    r.extend_to_include(t(20));
+    assert!(r == range!('[' t(10), t(20) ']'));
+    r.extend_to_include(t(30));
+    assert!(r == range!('[' t(10), t(30) ']'));
+}
+
+#[test]
+fn test_range_map() {
+    let r = range!('[' 1i32, 5i32 ')').map(|v| v as i64 * 10);
+    assert!(r == range!('[' 10i64, 50i64 ')'));
+
+    let r = range!('(', 5i32 ']').map(|v| Timespec::new(v as i64, 0));
+    assert!(r == range!('(', Timespec::new(6, 0) ')'));
+
+    let r: Range<i64> = Range::<i32>::empty().map(|v| v as i64);
+    assert!(r.is_empty());
+
+    let r = range!('(' 1.5f64, ')').map(|v| v.floor() as i32);
+    assert!(r == range!('[' 2i32, ')'));
+}
+
+fn random_bound_type<R: Rng>(rng: &mut R) -> Option<BoundType> {
+    match rng.gen_range(0u, 3) {
+        0 => None,
+        1 => Some(Inclusive),
+        _ => Some(Exclusive),
+    }
+}
+
+fn check_bytes_round_trip<T: PartialOrd+Normalizable+FixedWidth+Clone>(range: Range<T>) {
+    match Range::from_bytes(range.to_bytes().as_slice()) {
+        Ok(decoded) => assert!(range == decoded),
+        Err(err) => fail!("unexpected error {}", err),
+    }
+}
+
+#[test]
+fn test_bytes_round_trip() {
+    let mut rng = rand::task_rng();
+    for _ in range(0u, 1000) {
+        let (l, u) = (rng.gen_range(-100i32, 100), rng.gen_range(-100i32, 100));
+        let lower = random_bound_type(&mut rng).map(|t| RangeBound::new(l, t));
+        let upper = random_bound_type(&mut rng).map(|t| RangeBound::new(u, t));
+        check_bytes_round_trip(Range::new(lower, upper));
+
+        // Halved to keep clear of the values normalization can't increment
+        let (l, u) = (rng.gen::<i64>() / 2, rng.gen::<i64>() / 2);
+        let lower = random_bound_type(&mut rng).map(|t| RangeBound::new(l, t));
+        let upper = random_bound_type(&mut rng).map(|t| RangeBound::new(u, t));
+        check_bytes_round_trip(Range::new(lower, upper));
+
+        let l = Timespec::new(rng.gen_range(-100i64, 100), rng.gen_range(0i32, 1_000_000_000));
+        let u = Timespec::new(rng.gen_range(-100i64, 100), rng.gen_range(0i32, 1_000_000_000));
+        let lower = random_bound_type(&mut rng).map(|t| RangeBound::new(l, t));
+        let upper = random_bound_type(&mut rng).map(|t| RangeBound::new(u, t));
+        check_bytes_round_trip(Range::new(lower, upper));
+    }
+
+    check_bytes_round_trip(Range::<i32>::empty());
+    check_bytes_round_trip(Range::<i64>::new(None, None));
+}
+
+#[test]
+fn test_bytes_format() {
+    assert_eq!(vec![0b0000_0001u8], Range::<i32>::empty().to_bytes());
+    assert_eq!(vec![0b0000_1110u8, 0, 0, 0, 1, 0, 0, 0, 5],
+               range!('[' 1i32, 5i32 ')').to_bytes());
+    assert_eq!(vec![0b0000_1000u8, 0, 0, 0, 6], range!('(', 5i32 ']').to_bytes());
+    assert_eq!(vec![0u8], Range::<i32>::new(None, None).to_bytes());
+}
+
+#[test]
+fn test_from_bytes_invalid() {
+    match Range::<i32>::from_bytes([0b1000_0000]) {
+        Err(ref err) if err.kind == InvalidInput => {}
+        res => fail!("unexpected result {}", res),
+    }
+    match Range::<i32>::from_bytes([0b0000_0011, 0, 0, 0, 1]) {
+        Err(ref err) if err.kind == InvalidInput => {}
+        res => fail!("unexpected result {}", res),
+    }
+    match Range::<i32>::from_bytes([0, 1]) {
+        Err(ref err) if err.kind == InvalidInput => {}
+        res => fail!("unexpected result {}", res),
+    }
+    assert!(Range::<i32>::from_bytes([0b0000_0110, 0, 0]).is_err());
+    assert!(Range::<i32>::from_bytes([]).is_err());
+
+    // (2147483647,) can't be normalized
+    match Range::<i32>::from_bytes([0b0000_0010, 0x7f, 0xff, 0xff, 0xff]) {
+        Err(ref err) if err.kind == InvalidInput => {}
+        res => fail!("unexpected result {}", res),
+    }
+    match Range::<i32>::from_bytes([0b0001_1000, 0x7f, 0xff, 0xff, 0xff]) {
+        Err(ref err) if err.kind == InvalidInput => {}
+        res => fail!("unexpected result {}", res),
+    }
+    match Range::<Timespec>::from_bytes([0b0000_0110, 0, 0, 0, 0, 0, 0, 0, 0,
+                                         0x3b, 0x9a, 0xca, 0x00]) {
+        Err(ref err) if err.kind == InvalidInput => {}
+        Err(err) => fail!("unexpected error {}", err),
+        Ok(_) => fail!("unexpected success"),
+    }
+}
+
+#[test]
+fn test_discrete_len() {
+    assert_eq!(Some(1000000), range!('[' 1i32, 1000001i32 ')').len());
+    assert_eq!(Some(5), range!('(' 0i32, 5i32 ']').len());
+    assert_eq!(Some(0), Range::<i32>::empty().len());
+    assert_eq!(None, range!('[' 1i32, ')').len());
+    assert_eq!(None, range!('(', 1i32 ')').len());
+    assert_eq!(Some(u32::MAX as u64),
+               range!('[' i32::MIN, i32::MAX ')').len());
+    assert_eq!(Some(u64::MAX), range!('[' i64::MIN, i64::MAX ')').len());
+    assert_eq!(Some(3), range!('[' -1i64, 2i64 ')').len());
+}
+
+#[test]
+fn test_json() {
+    let r = range!('[' 1i32, 10i32 ')');
+    let encoded = json::encode(&r);
+    assert_eq!(r#"{"empty":false,"lower":1,"upper":10,"lower_inc":true,"upper_inc":false}"#,
+               encoded.as_slice());
+    assert_eq!(r, json::decode(encoded.as_slice()).unwrap());
+
+    let r = range!('(' 5i32, ')');
+    let encoded = json::encode(&r);
+    assert_eq!(r#"{"empty":false,"lower":6,"upper":null,"lower_inc":true,"upper_inc":false}"#,
+               encoded.as_slice());
+    assert_eq!(r, json::decode(encoded.as_slice()).unwrap());
+
+    let r = Range::<i32>::empty();
+    let encoded = json::encode(&r);
+    assert_eq!(r#"{"empty":true,"lower":null,"upper":null,"lower_inc":false,"upper_inc":false}"#,
+               encoded.as_slice());
+    assert_eq!(r, json::decode(encoded.as_slice()).unwrap());
+
+    let r = range!('[' 1.5f64, ')');
+    assert_eq!(r, json::decode(json::encode(&r).as_slice()).unwrap());
+}
+
+#[test]
+fn test_json_decode_normalizes() {
+    let r: Range<i32> = json::decode(r#"{"empty":false,"lower":1,"upper":5,
+                                         "lower_inc":true,"upper_inc":true}"#).unwrap();
+    assert_eq!(range!('[' 1i32, 6i32 ')'), r);
+
+    let r: Range<i32> = json::decode(r#"{"empty":false,"lower":5,"upper":5,
+                                         "lower_inc":true,"upper_inc":false}"#).unwrap();
+    assert!(r.is_empty());
+}
+
+#[test]
+fn test_json_decode_overflow() {
+    let r: json::DecodeResult<Range<i32>> =
+        json::decode(r#"{"empty":false,"lower":null,"upper":2147483647,
+                         "lower_inc":false,"upper_inc":true}"#);
+    assert!(r.is_err());
+
+    let r: json::DecodeResult<Range<i32>> =
+        json::decode(r#"{"empty":false,"lower":2147483647,"upper":null,
+                         "lower_inc":false,"upper_inc":false}"#);
+    assert!(r.is_err());
+}
+
+#[test]
+fn test_json_bound() {
+    let b: RangeBound<LowerBound, i32> = RangeBound::new(3, Exclusive);
+    let encoded = json::encode(&b);
+    assert_eq!(r#"{"value":3,"inclusive":false}"#, encoded.as_slice());
+    assert_eq!(b, json::decode(encoded.as_slice()).unwrap());
+
+    assert_eq!(r#""Inclusive""#, json::encode(&Inclusive).as_slice());
+    let type_: BoundType = json::decode(r#""Exclusive""#).unwrap();
+    assert!(type_ == Exclusive);
+}
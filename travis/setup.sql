@@ -1,3 +1,7 @@
 CREATE ROLE pass_user PASSWORD 'password' LOGIN;
 CREATE ROLE md5_user PASSWORD 'password' LOGIN;
+CREATE ROLE special_user PASSWORD 'p@ss/w:rd' LOGIN;
+SET password_encryption = 'scram-sha-256';
+CREATE ROLE scram_user PASSWORD 'password' LOGIN;
+CREATE ROLE gss_user LOGIN;
 CREATE EXTENSION hstore;